@@ -150,6 +150,44 @@ fn lookup(c: &mut Criterion) {
     }
 }
 
+fn lookup_mut(c: &mut Criterion) {
+    let mut m = IndexMap::default();
+    for i in KEY_DIST.take(SIZE) {
+        m.insert(DropType(i));
+    }
+    // Half the lookups hit a free slot.
+    for i in KEY_DIST.step_by(2) {
+        m.remove(i);
+    }
+
+    c.bench_function("lookup_mut", |b| {
+        b.iter(|| {
+            for i in KEY_DIST.take(SIZE) {
+                if let Some(val) = m.get_mut(i) {
+                    val.0 += 1;
+                }
+            }
+        });
+    });
+
+    if BENCH_HASHMAP {
+        let mut m = FxHashMap::default();
+        for i in KEY_DIST.take(SIZE).skip(1).step_by(2) {
+            m.insert(i, DropType(i));
+        }
+
+        c.bench_function("hash_map-lookup_mut", |b| {
+            b.iter(|| {
+                for i in KEY_DIST.take(SIZE) {
+                    if let Some(val) = m.get_mut(&i) {
+                        val.0 += 1;
+                    }
+                }
+            });
+        });
+    }
+}
+
 fn lookup_fail(c: &mut Criterion) {
     let mut m = IndexMap::default();
     let mut iter = KEY_DIST;
@@ -212,6 +250,120 @@ fn bench_iter(c: &mut Criterion) {
     }
 }
 
+fn sum_sparse(c: &mut Criterion) {
+    let mut m = IndexMap::default();
+    for i in KEY_DIST.take(SIZE) {
+        m.insert(i as i64);
+    }
+    for i in (0..SIZE).step_by(2) {
+        m.remove(i);
+    }
+    // Leave a long run of free slots at the end, which `fold` does not need to walk.
+    for i in SIZE / 2..SIZE {
+        m.remove(i);
+    }
+
+    c.bench_function("sum_sparse", |b| {
+        b.iter(|| {
+            black_box(m.values().sum::<i64>());
+        });
+    });
+
+    c.bench_function("sum_sparse_next", |b| {
+        b.iter(|| {
+            // A `for` loop goes through `next`, so this walks every slot like the default `sum`.
+            let mut sum = 0;
+            for v in m.values() {
+                sum += v;
+            }
+            black_box(sum);
+        });
+    });
+
+    if BENCH_HASHMAP {
+        let mut m = FxHashMap::default();
+        for i in KEY_DIST.take(SIZE) {
+            m.insert(i, i as i64);
+        }
+        for i in (0..SIZE).step_by(2) {
+            m.remove(&i);
+        }
+        for i in SIZE / 2..SIZE {
+            m.remove(&i);
+        }
+
+        c.bench_function("hash_map-sum_sparse", |b| {
+            b.iter(|| {
+                black_box(m.values().sum::<i64>());
+            });
+        });
+    }
+}
+
+fn find_map_sparse(c: &mut Criterion) {
+    let mut m = IndexMap::default();
+    for i in KEY_DIST.take(SIZE) {
+        m.insert(DropType(i));
+    }
+    for i in (0..SIZE).step_by(2) {
+        m.remove(i);
+    }
+
+    c.bench_function("find_map_sparse", |b| {
+        b.iter(|| {
+            black_box(m.find_map_value(|_, v| if v.0 == SIZE / 2 + 1 { Some(v.0) } else { None }));
+        });
+    });
+
+    if BENCH_HASHMAP {
+        let mut m = FxHashMap::default();
+        for i in KEY_DIST.take(SIZE) {
+            m.insert(i, DropType(i));
+        }
+        for i in (0..SIZE).step_by(2) {
+            m.remove(&i);
+        }
+
+        c.bench_function("hash_map-find_map_sparse", |b| {
+            b.iter(|| {
+                black_box(
+                    m.values()
+                        .find_map(|v| if v.0 == SIZE / 2 + 1 { Some(v.0) } else { None }),
+                );
+            });
+        });
+    }
+}
+
+fn append(c: &mut Criterion) {
+    c.bench_function("append", |b| {
+        b.iter(|| {
+            let mut src = IndexMap::with_capacity(SIZE);
+            for i in KEY_DIST.take(SIZE) {
+                src.insert(DropType(i));
+            }
+            let mut m = IndexMap::new();
+            m.append(&mut src);
+            black_box(&mut m);
+        });
+    });
+
+    if BENCH_HASHMAP {
+        c.bench_function("hash_map-append", |b| {
+            b.iter(|| {
+                let mut src = FxHashMap::default();
+                src.reserve(SIZE);
+                for i in KEY_DIST.take(SIZE) {
+                    src.insert(i, DropType(i));
+                }
+                let mut m = FxHashMap::default();
+                m.extend(src.drain());
+                black_box(&mut m);
+            });
+        });
+    }
+}
+
 fn clone_small(c: &mut Criterion) {
     let mut m = IndexMap::new();
     for i in 0..10 {
@@ -264,15 +416,52 @@ fn clone_large(c: &mut Criterion) {
     }
 }
 
+fn clone_fragmented(c: &mut Criterion) {
+    let mut m = IndexMap::new();
+    for i in 0..1000 {
+        m.insert(DropType(i));
+    }
+    for i in (0..1000).step_by(2) {
+        m.remove(i);
+    }
+
+    c.bench_function("clone_fragmented", |b| {
+        b.iter(|| {
+            black_box(m.clone());
+        });
+    });
+
+    if BENCH_HASHMAP {
+        let mut m = FxHashMap::default();
+        for i in 0..1000 {
+            m.insert(i, DropType(i));
+        }
+        for i in (0..1000).step_by(2) {
+            m.remove(&i);
+        }
+
+        c.bench_function("hash_map-clone_fragmented", |b| {
+            b.iter(|| {
+                black_box(m.clone());
+            });
+        });
+    }
+}
+
 criterion_group!(
     benches,
     insert,
     grow_insert,
     insert_erase,
     lookup,
+    lookup_mut,
     lookup_fail,
     bench_iter,
+    sum_sparse,
+    find_map_sparse,
+    append,
     clone_small,
-    clone_large
+    clone_large,
+    clone_fragmented
 );
 criterion_main!(benches);
@@ -0,0 +1,128 @@
+use super::IndexMap;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+/// A builder for configuring an [`IndexMap`] before creating it.
+///
+/// This `struct` is created by the [`builder`](IndexMap::builder) method on [`IndexMap`]. See its
+/// documentation for more.
+///
+/// # Example
+/// ```
+/// use index_map::IndexMap;
+///
+/// let map: IndexMap<&str> = IndexMap::builder()
+///     .capacity(10)
+///     .track_insertion_order()
+///     .build();
+/// assert!(map.capacity() >= 10);
+/// ```
+pub struct Builder<T> {
+    capacity: usize,
+    track_insertion_order: bool,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Builder<T> {
+    fn clone(&self) -> Self {
+        Self {
+            capacity: self.capacity,
+            track_insertion_order: self.track_insertion_order,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Builder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Builder")
+            .field("capacity", &self.capacity)
+            .field("track_insertion_order", &self.track_insertion_order)
+            .finish()
+    }
+}
+
+impl<T> Default for Builder<T> {
+    fn default() -> Self {
+        Self {
+            capacity: 0,
+            track_insertion_order: false,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Builder<T> {
+    /// Sets the capacity the map is created with, as in
+    /// [`with_capacity`](IndexMap::with_capacity).
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let map: IndexMap<&str> = IndexMap::builder().capacity(10).build();
+    /// assert!(map.capacity() >= 10);
+    /// ```
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Makes the map record the order in which values are inserted, as in
+    /// [`with_insertion_order_tracking`](IndexMap::with_insertion_order_tracking).
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::builder().track_insertion_order().build();
+    /// let a = map.insert("a");
+    /// map.insert("b");
+    /// map.remove(a);
+    /// map.insert("c");
+    ///
+    /// let values: Vec<_> = map.iter_insertion_order().map(|(_, v)| *v).collect();
+    /// assert_eq!(values, ["b", "c"]);
+    /// ```
+    pub fn track_insertion_order(mut self) -> Self {
+        self.track_insertion_order = true;
+        self
+    }
+
+    /// Creates the map with the configured options.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let map: IndexMap<&str> = IndexMap::builder().build();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn build(self) -> IndexMap<T> {
+        let mut map = IndexMap::with_capacity(self.capacity);
+        if self.track_insertion_order {
+            map.order = Some(Vec::with_capacity(self.capacity));
+        }
+        map
+    }
+}
+
+impl<T> IndexMap<T> {
+    /// Creates a [`Builder`] for configuring several options of a map at once.
+    ///
+    /// [`new`](IndexMap::new) and [`with_capacity`](IndexMap::with_capacity) remain the simplest
+    /// way to create a map with the default behaviour.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::builder().capacity(4).build();
+    /// map.insert("a");
+    /// assert_eq!(map[0], "a");
+    /// ```
+    pub fn builder() -> Builder<T> {
+        Builder::default()
+    }
+}
@@ -0,0 +1,53 @@
+use super::IndexMap;
+
+/// How the free list changed when a slot was freed by [`remove_debug`](IndexMap::remove_debug).
+///
+/// Only available with the `debug_checks` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FreeListOp {
+    /// The freed slot, which is now the head of the free list.
+    pub new_head: usize,
+    /// The slot the freed slot now links to, i.e. the one that will be reused after it.
+    pub next: Option<usize>,
+    /// The head of the free list before the removal.
+    pub prev_head: Option<usize>,
+}
+
+impl<T> IndexMap<T> {
+    /// Removes a key from the map like [`remove`](IndexMap::remove), also returning how the free
+    /// list changed, so tests can check it.
+    ///
+    /// Only available with the `debug_checks` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::{FreeListOp, IndexMap};
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert("a");
+    /// let b = map.insert("b");
+    ///
+    /// let (val, op) = map.remove_debug(a).unwrap();
+    /// assert_eq!(val, "a");
+    /// assert_eq!(op, FreeListOp { new_head: a, next: None, prev_head: None });
+    ///
+    /// let (_, op) = map.remove_debug(b).unwrap();
+    /// assert_eq!(op, FreeListOp { new_head: b, next: Some(a), prev_head: Some(a) });
+    ///
+    /// assert!(map.remove_debug(b).is_none());
+    /// ```
+    pub fn remove_debug(&mut self, key: usize) -> Option<(T, FreeListOp)> {
+        let prev_head = self.head;
+        let val = self.remove(key)?;
+
+        let op = FreeListOp {
+            new_head: self
+                .head
+                .expect("removed slot was not linked into the free list"),
+            next: self.data[key].as_ref().into_index(),
+            prev_head,
+        };
+
+        Some((val, op))
+    }
+}
@@ -0,0 +1,484 @@
+use super::IndexMap;
+use core::fmt;
+
+/// A view into a single entry in a map, which may either be vacant or occupied.
+///
+/// This `enum` is constructed from the [`entry`](IndexMap::entry) method on [`IndexMap`].
+pub enum Entry<'a, T> {
+    /// An occupied entry.
+    Occupied(OccupiedEntry<'a, T>),
+    /// A vacant entry.
+    Vacant(VacantEntry<'a, T>),
+}
+
+impl<T: fmt::Debug> fmt::Debug for Entry<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::Occupied(o) => f.debug_tuple("Entry").field(o).finish(),
+            Entry::Vacant(v) => f.debug_tuple("Entry").field(v).finish(),
+        }
+    }
+}
+
+impl<'a, T> Entry<'a, T> {
+    /// Returns the key of this entry.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map: IndexMap<&str> = IndexMap::new();
+    /// assert_eq!(map.entry(3).key(), 3);
+    /// ```
+    pub fn key(&self) -> usize {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default if empty, and returns a mutable
+    /// reference to the value in the entry.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// *map.entry(2).or_insert(1) += 10;
+    /// assert_eq!(map[2], 11);
+    ///
+    /// *map.entry(2).or_insert(1) += 10;
+    /// assert_eq!(map[2], 21);
+    /// ```
+    pub fn or_insert(self, default: T) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default function if empty,
+    /// and returns a mutable reference to the value in the entry.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let s = "hoho".to_string();
+    ///
+    /// map.entry(0).or_insert_with(|| s);
+    /// assert_eq!(map[0], "hoho".to_string());
+    /// ```
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting, if empty, the result of the default function
+    /// called with the key of the entry, and returns a mutable reference to the value in the
+    /// entry.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.entry(4).or_insert_with_key(|key| key * 2);
+    /// assert_eq!(map[4], 8);
+    /// ```
+    pub fn or_insert_with_key<F: FnOnce(usize) -> T>(self, default: F) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting, if empty, the result of the default function
+    /// called with the key of the entry, and returns the key along with a mutable reference to
+    /// the value in the entry.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let (key, val) = map.entry(4).or_insert_with_key_mut(|key| key * 2);
+    /// *val += 1;
+    /// assert_eq!(key, 4);
+    /// assert_eq!(map[4], 9);
+    /// ```
+    pub fn or_insert_with_key_mut<F: FnOnce(usize) -> T>(self, default: F) -> (usize, &'a mut T) {
+        let key = self.key();
+        (key, self.or_insert_with_key(default))
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any potential inserts into
+    /// the map.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    ///
+    /// map.entry(0).and_modify(|e| *e += 1).or_insert(42);
+    /// assert_eq!(map[0], 42);
+    ///
+    /// map.entry(0).and_modify(|e| *e += 1).or_insert(42);
+    /// assert_eq!(map[0], 43);
+    /// ```
+    pub fn and_modify<F: FnOnce(&mut T)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, T: Default> Entry<'a, T> {
+    /// Ensures a value is in the entry by inserting the default value if empty, and returns a
+    /// mutable reference to the value in the entry.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map: IndexMap<Option<u32>> = IndexMap::new();
+    /// map.entry(1).or_default();
+    /// assert_eq!(map[1], None);
+    /// ```
+    pub fn or_default(self) -> &'a mut T {
+        self.or_insert_with(T::default)
+    }
+}
+
+/// A view into an occupied entry in a `IndexMap`. It is part of the [`Entry`] enum.
+pub struct OccupiedEntry<'a, T> {
+    map: &'a mut IndexMap<T>,
+    key: usize,
+}
+
+impl<T: fmt::Debug> fmt::Debug for OccupiedEntry<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedEntry")
+            .field("key", &self.key())
+            .field("value", self.get())
+            .finish()
+    }
+}
+
+impl<'a, T> OccupiedEntry<'a, T> {
+    /// Gets the key in the entry.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(12);
+    /// assert_eq!(map.entry(0).key(), 0);
+    /// ```
+    pub fn key(&self) -> usize {
+        self.key
+    }
+
+    /// Gets a reference to the value in the entry.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::{Entry, IndexMap};
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(12);
+    ///
+    /// if let Entry::Occupied(o) = map.entry(0) {
+    ///     assert_eq!(o.get(), &12);
+    /// }
+    /// ```
+    pub fn get(&self) -> &T {
+        &self.map[self.key]
+    }
+
+    /// Gets a mutable reference to the value in the entry.
+    ///
+    /// If you need a reference to the `OccupiedEntry` which may outlive the destruction of the
+    /// `Entry` value, see [`into_mut`](OccupiedEntry::into_mut).
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::{Entry, IndexMap};
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(12);
+    ///
+    /// if let Entry::Occupied(mut o) = map.entry(0) {
+    ///     *o.get_mut() += 10;
+    ///     assert_eq!(*o.get(), 22);
+    /// }
+    /// assert_eq!(map[0], 22);
+    /// ```
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.map[self.key]
+    }
+
+    /// Converts the entry into a mutable reference to its value, with a lifetime bound to the map
+    /// itself.
+    ///
+    /// If you need multiple references to the `OccupiedEntry`, see
+    /// [`get_mut`](OccupiedEntry::get_mut).
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::{Entry, IndexMap};
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(12);
+    ///
+    /// if let Entry::Occupied(o) = map.entry(0) {
+    ///     *o.into_mut() += 10;
+    /// }
+    /// assert_eq!(map[0], 22);
+    /// ```
+    pub fn into_mut(self) -> &'a mut T {
+        let OccupiedEntry { map, key } = self;
+        &mut map[key]
+    }
+
+    /// Sets the value of the entry, and returns the entry's old value.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::{Entry, IndexMap};
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(12);
+    ///
+    /// if let Entry::Occupied(mut o) = map.entry(0) {
+    ///     assert_eq!(o.insert(15), 12);
+    /// }
+    /// assert_eq!(map[0], 15);
+    /// ```
+    pub fn insert(&mut self, value: T) -> T {
+        core::mem::replace(self.get_mut(), value)
+    }
+
+    /// Takes the value out of the entry, and returns it. The key is freed exactly as with
+    /// [`IndexMap::remove`].
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::{Entry, IndexMap};
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(12);
+    ///
+    /// if let Entry::Occupied(o) = map.entry(0) {
+    ///     assert_eq!(o.remove(), 12);
+    /// }
+    /// assert!(!map.contains_key(0));
+    /// ```
+    pub fn remove(self) -> T {
+        self.remove_entry().1
+    }
+
+    /// Takes the ownership of the key and value from the map.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::{Entry, IndexMap};
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(12);
+    ///
+    /// if let Entry::Occupied(o) = map.entry(0) {
+    ///     assert_eq!(o.remove_entry(), (0, 12));
+    /// }
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn remove_entry(self) -> (usize, T) {
+        self.map.remove_entry(self.key).unwrap()
+    }
+
+    /// Takes the value out of the entry and calls `f` with the key and the value. If `f` returns
+    /// a new value, it is put back under the same key, otherwise the entry is removed, freeing
+    /// the key exactly as with [`IndexMap::remove`].
+    ///
+    /// Returns the entry in its new state. If the map tracks insertion order, a value put back
+    /// keeps the position of the one it replaced. If `f` panics, the entry is removed.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::{Entry, IndexMap};
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert(2);
+    ///
+    /// for _ in 0..2 {
+    ///     if let Entry::Occupied(o) = map.entry(a) {
+    ///         o.replace_entry_with(|_, hits| if hits > 1 { Some(hits - 1) } else { None });
+    ///     }
+    /// }
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn replace_entry_with<F>(self, f: F) -> Entry<'a, T>
+    where
+        F: FnOnce(usize, T) -> Option<T>,
+    {
+        let OccupiedEntry { map, key } = self;
+        let seq = map.order.as_ref().map(|order| order[key]);
+        // Removing first keeps the map consistent while `f` runs. The key ends up at the head of
+        // the free list, so putting a value back doesn't have to walk it.
+        let val = map.remove(key).expect("occupied entry has no value");
+
+        match f(key, val) {
+            Some(val) => {
+                map.insert_vacant(key, val);
+                if let (Some(order), Some(seq)) = (&mut map.order, seq) {
+                    order[key] = seq;
+                }
+                Entry::Occupied(OccupiedEntry { map, key })
+            }
+            None => Entry::Vacant(VacantEntry { map, key }),
+        }
+    }
+}
+
+/// A view into a vacant entry in a `IndexMap`. It is part of the [`Entry`] enum.
+pub struct VacantEntry<'a, T> {
+    map: &'a mut IndexMap<T>,
+    key: usize,
+}
+
+impl<T> fmt::Debug for VacantEntry<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("VacantEntry").field(&self.key()).finish()
+    }
+}
+
+impl<'a, T> VacantEntry<'a, T> {
+    /// Gets the key that would be used when inserting a value through the `VacantEntry`.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map: IndexMap<u32> = IndexMap::new();
+    /// assert_eq!(map.entry(5).key(), 5);
+    /// ```
+    pub fn key(&self) -> usize {
+        self.key
+    }
+
+    /// Returns `true` if inserting into this entry would have to extend the map's backing storage,
+    /// rather than reuse a free slot.
+    ///
+    /// Extending the storage pads it with free slots up to the key, and may reallocate if the
+    /// [`capacity()`](IndexMap::capacity) is not large enough.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::{Entry, IndexMap};
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert("a");
+    /// map.insert("b");
+    /// map.remove(a);
+    ///
+    /// if let Entry::Vacant(v) = map.entry(a) {
+    ///     assert!(!v.would_grow());
+    /// }
+    /// if let Entry::Vacant(v) = map.entry(10) {
+    ///     assert!(v.would_grow());
+    /// }
+    /// ```
+    pub fn would_grow(&self) -> bool {
+        self.key >= self.map.data.len()
+    }
+
+    /// Sets the value of the entry with the `VacantEntry`'s key, and returns a mutable reference
+    /// to it.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::{Entry, IndexMap};
+    ///
+    /// let mut map = IndexMap::new();
+    ///
+    /// if let Entry::Vacant(v) = map.entry(2) {
+    ///     *v.insert(37) += 1;
+    /// }
+    /// assert_eq!(map[2], 38);
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn insert(self, value: T) -> &'a mut T {
+        self.map.insert_vacant(self.key, value)
+    }
+}
+
+impl<T> IndexMap<T> {
+    /// Gets the given key's corresponding entry in the map for in-place manipulation.
+    ///
+    /// Unlike [`insert`](IndexMap::insert), inserting through a vacant entry uses exactly the
+    /// given key. If the key is past the end of the map, the map is padded with free slots up to
+    /// it, which will be handed out by subsequent inserts. If the key is a free slot which isn't
+    /// at the head of the free list, the free list has to be walked to unlink it.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut counts = IndexMap::new();
+    ///
+    /// for key in [3, 1, 3, 7, 3] {
+    ///     *counts.entry(key).or_insert(0) += 1;
+    /// }
+    ///
+    /// assert_eq!(counts[3], 3);
+    /// assert_eq!(counts[1], 1);
+    /// assert_eq!(counts[7], 1);
+    /// assert_eq!(counts.get(0), None);
+    /// ```
+    pub fn entry(&mut self, key: usize) -> Entry<'_, T> {
+        if self.contains_key(key) {
+            Entry::Occupied(OccupiedEntry { map: self, key })
+        } else {
+            Entry::Vacant(VacantEntry { map: self, key })
+        }
+    }
+
+    /// Gets a vacant entry for the key the next [`insert`](IndexMap::insert) would return, so
+    /// that the value can be built knowing its own key.
+    ///
+    /// Inserting through the entry is the same as calling `insert`: the most recently freed key is
+    /// reused if there is one, otherwise a new slot is added at the end. Dropping the entry
+    /// without inserting leaves the map untouched.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// struct Node {
+    ///     id: usize,
+    /// }
+    ///
+    /// let mut map = IndexMap::new();
+    /// let entry = map.vacant_entry();
+    /// let id = entry.key();
+    /// entry.insert(Node { id });
+    /// assert_eq!(map[id].id, id);
+    /// ```
+    pub fn vacant_entry(&mut self) -> VacantEntry<'_, T> {
+        let key = self.head.unwrap_or(self.data.len());
+        VacantEntry { map: self, key }
+    }
+}
@@ -0,0 +1,50 @@
+use core::fmt;
+
+/// The reason a key could not be resolved by
+/// [`get_disjoint_mut_report`](crate::IndexMap::get_disjoint_mut_report).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DisjointError {
+    /// The key is not present in the map.
+    Absent,
+    /// The key was already requested earlier in the same call.
+    Duplicate,
+}
+
+impl fmt::Display for DisjointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisjointError::Absent => f.write_str("key is not present in the map"),
+            DisjointError::Duplicate => f.write_str("key was requested more than once"),
+        }
+    }
+}
+
+/// The error returned by [`append_preserving`](crate::IndexMap::append_preserving) when both maps
+/// contain the same key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyCollision {
+    /// The first key, in ascending order, which is present in both maps.
+    pub key: usize,
+}
+
+impl fmt::Display for KeyCollision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "key {} is present in both maps", self.key)
+    }
+}
+
+/// The error returned by [`try_insert`](crate::IndexMap::try_insert) when the key is already
+/// occupied, handing back the value which wasn't inserted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OccupiedError<T> {
+    /// The key which is already occupied.
+    pub key: usize,
+    /// The value which was not inserted.
+    pub value: T,
+}
+
+impl<T> fmt::Display for OccupiedError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "key {} is already occupied", self.key)
+    }
+}
@@ -0,0 +1,174 @@
+use super::IndexMap;
+
+/// A key handed out by [`insert_handle`](IndexMap::insert_handle), which makes it explicit that a
+/// `usize` refers to a value in an [`IndexMap`].
+///
+/// A handle only holds the key, not a borrow of the map, so it can be kept across mutations of the
+/// map. Like any key, it stops referring to its value once the value is removed, and may later
+/// refer to a different value if the key is reused.
+///
+/// # Example
+/// ```
+/// use index_map::IndexMap;
+///
+/// let mut map = IndexMap::new();
+/// let a = map.insert_handle("a");
+/// assert_eq!(map.get(a), Some(&"a"));
+/// assert_eq!(a.key(), 0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Handle(usize);
+
+impl Handle {
+    /// Returns the key this handle refers to.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    /// let b = map.insert_handle("b");
+    /// assert_eq!(b.key(), 1);
+    /// ```
+    pub fn key(self) -> usize {
+        self.0
+    }
+
+    /// Returns a reference to the value this handle refers to in `map`.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert_handle("a");
+    /// assert_eq!(a.resolve(&map), Some(&"a"));
+    /// map.remove(a.key());
+    /// assert_eq!(a.resolve(&map), None);
+    /// ```
+    pub fn resolve<T>(self, map: &IndexMap<T>) -> Option<&T> {
+        map.get(self)
+    }
+
+    /// Returns a mutable reference to the value this handle refers to in `map`.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert_handle(1);
+    /// if let Some(val) = a.resolve_mut(&mut map) {
+    ///     *val += 1;
+    /// }
+    /// assert_eq!(map[a.key()], 2);
+    /// ```
+    pub fn resolve_mut<T>(self, map: &mut IndexMap<T>) -> Option<&mut T> {
+        map.get_mut(self)
+    }
+}
+
+impl From<Handle> for usize {
+    fn from(handle: Handle) -> usize {
+        handle.0
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for usize {}
+    impl Sealed for super::Handle {}
+}
+
+/// A type which can be used to look up a value in an [`IndexMap`]: either a plain `usize` key or
+/// a [`Handle`].
+///
+/// This trait is sealed, and cannot be implemented outside this crate. Keeping `usize` as the only
+/// integer type implementing it means integer literals still infer to `usize` when passed as keys.
+pub trait Key: sealed::Sealed {
+    /// Returns the raw key.
+    fn into_key(self) -> usize;
+}
+
+impl Key for usize {
+    fn into_key(self) -> usize {
+        self
+    }
+}
+
+impl Key for Handle {
+    fn into_key(self) -> usize {
+        self.0
+    }
+}
+
+impl<T> IndexMap<T> {
+    /// Inserts a value into the map, returning a [`Handle`] to it rather than a bare key.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert_handle("a");
+    /// map.insert("b");
+    /// assert_eq!(map.get(a), Some(&"a"));
+    /// ```
+    pub fn insert_handle(&mut self, value: T) -> Handle {
+        Handle(self.insert(value))
+    }
+
+    /// An iterator visiting all the [`Handle`]s in ascending order, like
+    /// [`keys`](IndexMap::keys).
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert_handle("a");
+    /// let b = map.insert_handle("b");
+    /// assert_eq!(map.handles().collect::<Vec<_>>(), [a, b]);
+    /// ```
+    pub fn handles(&self) -> impl Iterator<Item = Handle> + '_ {
+        self.keys().map(Handle)
+    }
+
+    /// An iterator visiting all the entries in ascending order of keys, with a [`Handle`] to each
+    /// value rather than a bare key, like [`iter`](IndexMap::iter).
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert_handle("a");
+    /// for (handle, val) in map.handle_iter() {
+    ///     assert_eq!(handle, a);
+    ///     assert_eq!(*val, "a");
+    /// }
+    /// ```
+    pub fn handle_iter(&self) -> impl Iterator<Item = (Handle, &T)> + '_ {
+        self.iter().map(|(key, val)| (Handle(key), val))
+    }
+
+    /// Creates a consuming iterator visiting all the entries in ascending order of keys, with a
+    /// [`Handle`] to each value rather than a bare key, like
+    /// [`into_iter`](IntoIterator::into_iter).
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert_handle("a");
+    /// let b = map.insert_handle("b");
+    /// let entries: Vec<_> = map.into_handle_iter().collect();
+    /// assert_eq!(entries, [(a, "a"), (b, "b")]);
+    /// ```
+    pub fn into_handle_iter(self) -> impl Iterator<Item = (Handle, T)> {
+        self.into_iter().map(|(key, val)| (Handle(key), val))
+    }
+}
@@ -1,6 +1,9 @@
 use super::{IndexMap, OptionIndex};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
 use core::fmt;
 use core::iter::{Enumerate, ExactSizeIterator, IntoIterator, Iterator};
+use core::ops::RangeBounds;
 use core::slice;
 
 /// An iterator over the entries of a `IndexMap`.
@@ -40,7 +43,12 @@ impl<'a, T> Iterator for Iter<'a, T> {
     type Item = (usize, &'a T);
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some((i, item)) = self.inner.next() {
+        // All the remaining slots are free, there is no need to walk them.
+        if self.len == 0 {
+            return None;
+        }
+
+        for (i, item) in self.inner.by_ref() {
             if let OptionIndex::Some(val) = item {
                 self.len -= 1;
                 return Some((i, val));
@@ -52,6 +60,48 @@ impl<'a, T> Iterator for Iter<'a, T> {
     fn size_hint(&self) -> (usize, Option<usize>) {
         (self.len, Some(self.len))
     }
+
+    // The number of values left is tracked, so there is no need to walk the remaining slots.
+    fn count(self) -> usize {
+        self.len
+    }
+
+    // Overridden so that `sum`, `for_each` and friends can stop walking as soon as every value has
+    // been visited, rather than checking each free slot that trails the last one.
+    fn fold<B, F>(self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, Self::Item) -> B,
+    {
+        let mut len = self.len;
+        let mut acc = init;
+
+        for (i, item) in self.inner {
+            if len == 0 {
+                break;
+            }
+            if let OptionIndex::Some(val) = item {
+                len -= 1;
+                acc = f(acc, (i, val));
+            }
+        }
+        acc
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+
+        for (i, item) in self.inner.by_ref().rev() {
+            if let OptionIndex::Some(val) = item {
+                self.len -= 1;
+                return Some((i, val));
+            }
+        }
+        None
+    }
 }
 
 impl<T> ExactSizeIterator for Iter<'_, T> {}
@@ -85,16 +135,23 @@ impl<'a, T> IntoIterator for &'a IndexMap<T> {
 pub struct IterMut<'a, T> {
     inner: Enumerate<slice::IterMut<'a, OptionIndex<T>>>,
     len: usize,
+    // The key of the first slot in `inner`, for iterators over only part of the map.
+    offset: usize,
 }
 
 impl<'a, T> Iterator for IterMut<'a, T> {
     type Item = (usize, &'a mut T);
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some((i, item)) = self.inner.next() {
+        // All the remaining slots are free, there is no need to walk them.
+        if self.len == 0 {
+            return None;
+        }
+
+        for (i, item) in self.inner.by_ref() {
             if let OptionIndex::Some(val) = item {
                 self.len -= 1;
-                return Some((i, val));
+                return Some((i + self.offset, val));
             }
         }
         None
@@ -105,6 +162,22 @@ impl<'a, T> Iterator for IterMut<'a, T> {
     }
 }
 
+impl<T> DoubleEndedIterator for IterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+
+        for (i, item) in self.inner.by_ref().rev() {
+            if let OptionIndex::Some(val) = item {
+                self.len -= 1;
+                return Some((i + self.offset, val));
+            }
+        }
+        None
+    }
+}
+
 impl<T> ExactSizeIterator for IterMut<'_, T> {}
 
 impl<'a, T> IntoIterator for &'a mut IndexMap<T> {
@@ -115,6 +188,7 @@ impl<'a, T> IntoIterator for &'a mut IndexMap<T> {
         IterMut {
             len: self.len(),
             inner: self.data.iter_mut().enumerate(),
+            offset: 0,
         }
     }
 }
@@ -142,7 +216,12 @@ impl<T> Iterator for IntoIter<T> {
     type Item = (usize, T);
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some((i, item)) = self.inner.next() {
+        // All the remaining slots are free, there is no need to walk them.
+        if self.len == 0 {
+            return None;
+        }
+
+        for (i, item) in self.inner.by_ref() {
             if let OptionIndex::Some(item) = item {
                 self.len -= 1;
                 return Some((i, item));
@@ -154,6 +233,26 @@ impl<T> Iterator for IntoIter<T> {
     fn size_hint(&self) -> (usize, Option<usize>) {
         (self.len, Some(self.len))
     }
+
+    fn count(self) -> usize {
+        self.len
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+
+        for (i, item) in self.inner.by_ref().rev() {
+            if let OptionIndex::Some(item) = item {
+                self.len -= 1;
+                return Some((i, item));
+            }
+        }
+        None
+    }
 }
 
 impl<T> ExactSizeIterator for IntoIter<T> {}
@@ -192,7 +291,12 @@ impl<T> Iterator for Drain<'_, T> {
     type Item = (usize, T);
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some((i, item)) = self.inner.next() {
+        // All the remaining slots are free, there is no need to walk them.
+        if self.len == 0 {
+            return None;
+        }
+
+        for (i, item) in self.inner.by_ref() {
             if let OptionIndex::Some(item) = item {
                 self.len -= 1;
                 return Some((i, item));
@@ -249,10 +353,37 @@ impl<'a, T> Iterator for Keys<'a, T> {
     fn size_hint(&self) -> (usize, Option<usize>) {
         (self.inner.len, Some(self.inner.len))
     }
+
+    fn count(self) -> usize {
+        self.inner.len
+    }
+
+    fn fold<B, F>(self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, Self::Item) -> B,
+    {
+        self.inner.fold(init, |acc, item| f(acc, item.0))
+    }
+}
+
+impl<T> DoubleEndedIterator for Keys<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next_back()?.0)
+    }
 }
 
 impl<T> ExactSizeIterator for Keys<'_, T> {}
 
+// Iterating a reference works on a copy, so the original can be iterated again.
+impl<'a, T> IntoIterator for &Keys<'a, T> {
+    type Item = usize;
+    type IntoIter = Keys<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.clone()
+    }
+}
+
 /// An iterator over the values of a `IndexMap`.
 ///
 /// This `struct` is created by the [`values`](IndexMap::values) method on [`IndexMap`]. See its
@@ -294,10 +425,37 @@ impl<'a, T> Iterator for Values<'a, T> {
     fn size_hint(&self) -> (usize, Option<usize>) {
         (self.inner.len, Some(self.inner.len))
     }
+
+    fn count(self) -> usize {
+        self.inner.len
+    }
+
+    fn fold<B, F>(self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, Self::Item) -> B,
+    {
+        self.inner.fold(init, |acc, item| f(acc, item.1))
+    }
+}
+
+impl<T> DoubleEndedIterator for Values<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next_back()?.1)
+    }
 }
 
 impl<T> ExactSizeIterator for Values<'_, T> {}
 
+// Iterating a reference works on a copy, so the original can be iterated again.
+impl<'a, T> IntoIterator for &Values<'a, T> {
+    type Item = &'a T;
+    type IntoIter = Values<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.clone()
+    }
+}
+
 /// A mutable iterator over the values of a `IndexMap`.
 ///
 /// This `struct` is created by the [`values_mut`](IndexMap::values_mut) method on [`IndexMap`]. See
@@ -328,9 +486,300 @@ impl<'a, T> Iterator for ValuesMut<'a, T> {
     }
 }
 
+impl<T> DoubleEndedIterator for ValuesMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next_back()?.1)
+    }
+}
+
 impl<T> ExactSizeIterator for ValuesMut<'_, T> {}
 
+/// An owning iterator over the keys of a `IndexMap`.
+///
+/// This `struct` is created by the [`into_keys`](IndexMap::into_keys) method on [`IndexMap`]. See
+/// its documentation for more.
+///
+/// # Example
+/// ```
+/// use index_map::IndexMap;
+///
+/// let mut map = IndexMap::new();
+/// map.insert("a");
+/// let iter_keys = map.into_keys();
+/// ```
+#[derive(Clone)]
+pub struct IntoKeys<T> {
+    inner: IntoIter<T>,
+}
+
+impl<T> Iterator for IntoKeys<T> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next()?.0)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.inner.len, Some(self.inner.len))
+    }
+
+    fn count(self) -> usize {
+        self.inner.len
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoKeys<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next_back()?.0)
+    }
+}
+
+impl<T> ExactSizeIterator for IntoKeys<T> {}
+
+/// An owning iterator over the values of a `IndexMap`.
+///
+/// This `struct` is created by the [`into_values`](IndexMap::into_values) method on [`IndexMap`].
+/// See its documentation for more.
+///
+/// # Example
+/// ```
+/// use index_map::IndexMap;
+///
+/// let mut map = IndexMap::new();
+/// map.insert("a");
+/// let iter_values = map.into_values();
+/// ```
+#[derive(Clone)]
+pub struct IntoValues<T> {
+    inner: IntoIter<T>,
+}
+
+impl<T> Iterator for IntoValues<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next()?.1)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.inner.len, Some(self.inner.len))
+    }
+
+    fn count(self) -> usize {
+        self.inner.len
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoValues<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some(self.inner.next_back()?.1)
+    }
+}
+
+impl<T> ExactSizeIterator for IntoValues<T> {}
+
+/// An iterator over the entries of a `IndexMap` in the order they were inserted.
+///
+/// This `struct` is created by the [`iter_insertion_order`](IndexMap::iter_insertion_order)
+/// method on [`IndexMap`]. See its documentation for more.
+///
+/// # Example
+/// ```
+/// use index_map::IndexMap;
+///
+/// let mut map = IndexMap::with_insertion_order_tracking();
+/// map.insert("a");
+/// let iter = map.iter_insertion_order();
+/// ```
+pub struct InsertionOrder<'a, T> {
+    map: &'a IndexMap<T>,
+    keys: alloc::vec::IntoIter<usize>,
+}
+
+impl<T> Clone for InsertionOrder<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map,
+            keys: self.keys.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for InsertionOrder<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, T> Iterator for InsertionOrder<'a, T> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next()?;
+        Some((key, &self.map[key]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for InsertionOrder<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next_back()?;
+        Some((key, &self.map[key]))
+    }
+}
+
+impl<T> ExactSizeIterator for InsertionOrder<'_, T> {}
+
 impl<T> IndexMap<T> {
+    /// An iterator visiting all key-value pairs in the order they were inserted, oldest first.
+    /// The iterator element type is `(usize, &T)`.
+    ///
+    /// Insertion order is only known for maps created with
+    /// [`with_insertion_order_tracking`](IndexMap::with_insertion_order_tracking). For any other
+    /// map, this visits the entries in ascending order of keys, same as [`iter`](IndexMap::iter).
+    ///
+    /// Creating the iterator sorts the keys, so it takes O(n log n) time and allocates.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::with_insertion_order_tracking();
+    /// let a = map.insert("a");
+    /// map.insert("b");
+    /// map.remove(a);
+    /// map.insert("c");
+    ///
+    /// // "c" reuses the key of "a", but was inserted after "b".
+    /// let entries: Vec<_> = map.iter_insertion_order().collect();
+    /// assert_eq!(entries, [(1, &"b"), (0, &"c")]);
+    /// ```
+    pub fn iter_insertion_order(&self) -> InsertionOrder<'_, T> {
+        let mut keys: Vec<_> = self.keys().collect();
+        if let Some(order) = &self.order {
+            keys.sort_unstable_by_key(|&key| order[key]);
+        }
+
+        InsertionOrder {
+            map: self,
+            keys: keys.into_iter(),
+        }
+    }
+
+    /// Calls the closure with every window of `W` consecutive values, in ascending order of their
+    /// keys, with mutable references to the values. Free slots are skipped over, so a window is
+    /// always made up of `W` values, even if their keys are not contiguous.
+    ///
+    /// Consecutive windows overlap, advancing by one value each time. If the map has fewer than
+    /// `W` values, the closure is never called.
+    ///
+    /// The windows are passed to a closure rather than yielded by an iterator, since overlapping
+    /// windows can't be held at the same time.
+    ///
+    /// # Panics
+    /// Panics if `W` is 0.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// for i in 0..5 {
+    ///     map.insert(i);
+    /// }
+    /// map.remove(2);
+    ///
+    /// // Running sum
+    /// map.for_each_window_mut(|[prev, curr]| *curr += *prev);
+    /// assert_eq!(map.values().copied().collect::<Vec<_>>(), [0, 1, 4, 8]);
+    /// ```
+    pub fn for_each_window_mut<const W: usize, F>(&mut self, mut f: F)
+    where
+        F: FnMut([&mut T; W]),
+    {
+        assert!(W != 0, "window size must be non-zero");
+
+        let mut values: Vec<_> = self.values_mut().collect();
+        if values.len() < W {
+            return;
+        }
+
+        for start in 0..=values.len() - W {
+            let mut window = values[start..start + W].iter_mut();
+            f(core::array::from_fn(|_| &mut **window.next().unwrap()));
+        }
+    }
+
+    /// An iterator visiting the `n` entries with the largest keys, or all of them if there are
+    /// fewer, in descending order of keys.
+    /// The iterator element type is `(usize, &T)`.
+    ///
+    /// The slots are scanned from the end, so only the slots past the `n`th largest key are ever
+    /// looked at.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// for i in 0..5 {
+    ///     map.insert(i);
+    /// }
+    /// map.remove(3);
+    ///
+    /// let last: Vec<_> = map.last_key_values(2).collect();
+    /// assert_eq!(last, [(4, &4), (2, &2)]);
+    /// assert_eq!(map.last_key_values(10).count(), 4);
+    /// ```
+    pub fn last_key_values(&self, n: usize) -> impl Iterator<Item = (usize, &T)> + '_ {
+        self.iter().rev().take(n)
+    }
+
+    /// Splits the map into two mutable iterators: one over the entries with keys less than `key`,
+    /// and one over the entries with keys greater than or equal to `key`. Since the two halves
+    /// never overlap, both can be used at the same time.
+    ///
+    /// This has to count the entries below `key`, so it takes time proportional to `key`.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// for i in 0..6 {
+    ///     map.insert(i);
+    /// }
+    /// map.remove(1);
+    ///
+    /// let (low, high) = map.split_at_key_mut(3);
+    /// assert_eq!(low.len(), 2);
+    /// for ((_, lo), (_, hi)) in low.zip(high) {
+    ///     *hi += *lo;
+    /// }
+    /// assert_eq!(map.values().copied().collect::<Vec<_>>(), [0, 2, 3, 6, 5]);
+    /// ```
+    pub fn split_at_key_mut(&mut self, key: usize) -> (IterMut<'_, T>, IterMut<'_, T>) {
+        let key = key.min(self.data.len());
+        let (low, high) = self.data.split_at_mut(key);
+        let low_len = low.iter().filter(|slot| slot.is_inner()).count();
+
+        (
+            IterMut {
+                len: low_len,
+                inner: low.iter_mut().enumerate(),
+                offset: 0,
+            },
+            IterMut {
+                len: self.len - low_len,
+                inner: high.iter_mut().enumerate(),
+                offset: key,
+            },
+        )
+    }
+
     /// An iterator visiting all keys in ascending order.
     /// The iterator element type is `usize`.
     ///
@@ -371,6 +820,50 @@ impl<T> IndexMap<T> {
         Values { inner: self.iter() }
     }
 
+    /// Creates a consuming iterator visiting all keys in ascending order. The map cannot be used
+    /// after calling this. The iterator element type is `usize`.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    /// map.insert("b");
+    /// map.insert("c");
+    /// map.remove(1);
+    ///
+    /// let keys: Vec<usize> = map.into_keys().collect();
+    /// assert_eq!(keys, [0, 2]);
+    /// ```
+    pub fn into_keys(self) -> IntoKeys<T> {
+        IntoKeys {
+            inner: self.into_iter(),
+        }
+    }
+
+    /// Creates a consuming iterator visiting all values in ascending order of their keys. The map
+    /// cannot be used after calling this. The iterator element type is `T`.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    /// map.insert("b");
+    /// map.insert("c");
+    /// map.remove(1);
+    ///
+    /// let values: Vec<&str> = map.into_values().collect();
+    /// assert_eq!(values, ["a", "c"]);
+    /// ```
+    pub fn into_values(self) -> IntoValues<T> {
+        IntoValues {
+            inner: self.into_iter(),
+        }
+    }
+
     /// An iterator visiting all values mutably in ascending order of their keys.
     /// The iterator element type is `&mut T`.
     ///
@@ -443,9 +936,64 @@ impl<T> IndexMap<T> {
         <&mut IndexMap<T>>::into_iter(self)
     }
 
+    /// An iterator visiting the key-value pairs whose values match the predicate, in ascending
+    /// order of keys.
+    /// The iterator element type is `(usize, &T)`.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(1);
+    /// map.insert(2);
+    /// map.insert(4);
+    ///
+    /// let even: Vec<_> = map.iter_filter(|v| v % 2 == 0).collect();
+    /// assert_eq!(even, [(1, &2), (2, &4)]);
+    /// ```
+    pub fn iter_filter<'a, P>(&'a self, mut predicate: P) -> impl Iterator<Item = (usize, &'a T)>
+    where
+        P: FnMut(&T) -> bool + 'a,
+    {
+        self.iter().filter(move |(_, val)| predicate(val))
+    }
+
+    /// An iterator visiting the key-value pairs whose values match the predicate, in ascending
+    /// order of keys, with mutable references to the values.
+    /// The iterator element type is `(usize, &mut T)`.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(1);
+    /// map.insert(2);
+    /// map.insert(4);
+    ///
+    /// for (_, val) in map.iter_filter_mut(|v| v % 2 == 0) {
+    ///     *val *= 10;
+    /// }
+    /// assert_eq!(map.values().copied().collect::<Vec<_>>(), [1, 20, 40]);
+    /// ```
+    pub fn iter_filter_mut<'a, P>(
+        &'a mut self,
+        mut predicate: P,
+    ) -> impl Iterator<Item = (usize, &'a mut T)>
+    where
+        P: FnMut(&T) -> bool + 'a,
+    {
+        self.iter_mut().filter(move |(_, val)| predicate(val))
+    }
+
     /// Clears the map, returning all key-value pairs as an iterator. Keeps the
     /// allocated memory for reuse.
     ///
+    /// The map is cleared even if the iterator is dropped before it is fully consumed. This also
+    /// resets the free list, since no free slots are left behind, so the next inserts get the keys
+    /// `0, 1, 2, ...`.
+    ///
     /// # Examples
     /// ```
     /// use index_map::IndexMap;
@@ -465,11 +1013,136 @@ impl<T> IndexMap<T> {
     pub fn drain(&mut self) -> Drain<'_, T> {
         let len = self.len();
         self.len = 0;
+        // Every slot is removed by the drain, even if the iterator is dropped early, so nothing is
+        // left for the free list to point to.
+        self.head = None;
+        if let Some(order) = &mut self.order {
+            order.clear();
+        }
         Drain {
             len,
             inner: self.data.drain(..).enumerate(),
         }
     }
+
+    /// Empties the map, returning all key-value pairs in ascending order of keys. Keeps the
+    /// allocated memory for reuse, and like [`drain`](IndexMap::drain), the next inserts get the
+    /// keys `0, 1, 2, ...`.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    /// let b = map.insert("b");
+    /// map.insert("c");
+    /// map.remove(b);
+    ///
+    /// assert_eq!(map.take_all(), [(0, "a"), (2, "c")]);
+    /// assert!(map.is_empty());
+    /// assert_eq!(map.insert("d"), 0);
+    /// ```
+    pub fn take_all(&mut self) -> Vec<(usize, T)> {
+        let mut entries = Vec::with_capacity(self.len);
+        entries.extend(self.drain());
+        entries
+    }
+
+    /// Clears the map, returning an iterator of `f(key, value)` for every entry in ascending
+    /// order of keys. Keeps the allocated memory for reuse, and like [`drain`](IndexMap::drain),
+    /// the map is completely reset even if the iterator is dropped before it is fully consumed.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(1);
+    /// let b = map.insert(2);
+    /// map.insert(3);
+    /// map.remove(b);
+    ///
+    /// let labels: Vec<_> = map.drain_map(|k, v| format!("{}={}", k, v)).collect();
+    /// assert_eq!(labels, ["0=1", "2=3"]);
+    /// assert!(map.is_empty());
+    /// assert_eq!(map.insert(4), 0);
+    /// ```
+    pub fn drain_map<'a, U, F>(&'a mut self, mut f: F) -> impl Iterator<Item = U> + 'a
+    where
+        F: FnMut(usize, T) -> U + 'a,
+    {
+        self.drain().map(move |(key, val)| f(key, val))
+    }
+
+    /// Clears the map, returning all key-value pairs sorted by their values with the given
+    /// comparator. Keeps the allocated memory for reuse.
+    ///
+    /// The sort is stable, so entries whose values compare equal are returned in ascending order
+    /// of their keys.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(3);
+    /// map.insert(1);
+    /// map.insert(2);
+    ///
+    /// let mut iter = map.drain_sorted_by(|a, b| a.cmp(b));
+    /// assert_eq!(iter.next(), Some((1, 1)));
+    /// assert_eq!(iter.next(), Some((2, 2)));
+    /// assert_eq!(iter.next(), Some((0, 3)));
+    /// assert_eq!(iter.next(), None);
+    ///
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn drain_sorted_by<F>(&mut self, mut compare: F) -> alloc::vec::IntoIter<(usize, T)>
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut entries: Vec<_> = self.drain().collect();
+        entries.sort_by(|(_, a), (_, b)| compare(a, b));
+        entries.into_iter()
+    }
+
+    /// Consumes the map, returning an iterator over only the entries whose keys fall within
+    /// `range`, in ascending order of keys. The values outside the range are dropped right away.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// for i in 0..10 {
+    ///     map.insert(i * 10);
+    /// }
+    /// map.remove(5);
+    ///
+    /// let page: Vec<_> = map.into_range(3..7).rev().collect();
+    /// assert_eq!(page, [(6, 60), (4, 40), (3, 30)]);
+    /// ```
+    pub fn into_range<R: RangeBounds<usize>>(self, range: R) -> IntoIter<T> {
+        let range = self.key_range(range);
+        let mut data = self.data;
+
+        data.truncate(range.end);
+        let mut len = 0;
+        for (i, slot) in data.iter_mut().enumerate() {
+            if i < range.start {
+                // Leave the slot free, so the iterator skips over it while keys stay aligned.
+                drop(slot.take());
+            } else if slot.is_inner() {
+                len += 1;
+            }
+        }
+
+        IntoIter {
+            len,
+            inner: data.into_iter().enumerate(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -541,13 +1214,13 @@ mod tests {
         let b = map.insert("b");
         map.insert("c");
         map.remove(b);
-        let mut iter = map.values().map(|v| *v);
+        let mut iter = map.values().copied();
         assert_eq!(iter.next(), Some("a"));
         assert_eq!(iter.next(), Some("c"));
         assert_eq!(iter.next(), None);
 
         assert_eq!(b, map.insert("b"));
-        let mut iter = map.values().map(|v| *v);
+        let mut iter = map.values().copied();
         assert_eq!(iter.next(), Some("a"));
         assert_eq!(iter.next(), Some("b"));
         assert_eq!(iter.next(), Some("c"));
@@ -562,7 +1235,7 @@ mod tests {
         map.insert(3);
         map.values_mut().for_each(|val| *val *= 2);
 
-        let mut map = map.values().map(|v| *v);
+        let mut map = map.values().copied();
 
         assert_eq!(map.next(), Some(2));
         assert_eq!(map.next(), Some(4));
@@ -47,6 +47,13 @@
 //! }
 //! ```
 //!
+//! # Key stability
+//! The key of a value never changes while it is in the map. Removing other entries, retaining,
+//! or shrinking the map only ever frees up keys, and never moves values to a different key. Once
+//! a value is removed, its key may be handed out again by a later insert. The only exceptions are
+//! methods which explicitly report the keys they move values to, like
+//! [`pop_front_swap`](IndexMap::pop_front_swap) and [`compact_with`](IndexMap::compact_with).
+//!
 //! # How it works
 //! It internally is based on a [`Vec`], where each element either stores a value, or stores the index
 //! of the next free element. Since it accommodates for empty elements in between, it can perform
@@ -79,7 +86,6 @@
 //!
 //! Op - remove(2)
 //! State:
-//! ```text
 //! .---.---.---.
 //! | * | - | 1 |
 //! '---'---'---'
@@ -95,24 +101,69 @@
 
 extern crate alloc;
 
+use alloc::collections::TryReserveError;
 use alloc::vec::Vec;
-
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+use core::iter::FromIterator;
+use core::mem;
+use core::ops::{Bound, Range, RangeBounds};
+
+mod builder;
+#[cfg(feature = "debug_checks")]
+mod debug_checks;
+mod entry;
+mod error;
+mod handle;
 mod iter;
 mod option_index;
-pub use iter::{Drain, IntoIter, Iter, IterMut, Keys, Values, ValuesMut};
+mod stats;
+mod view;
+pub use builder::Builder;
+#[cfg(feature = "debug_checks")]
+pub use debug_checks::FreeListOp;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use error::{DisjointError, KeyCollision, OccupiedError};
+pub use handle::{Handle, Key};
+pub use iter::{
+    Drain, InsertionOrder, IntoIter, IntoKeys, IntoValues, Iter, IterMut, Keys, Values, ValuesMut,
+};
 use option_index::OptionIndex;
+pub use stats::MapStats;
+pub use view::RestView;
 
 /// A map of `usize` to value, which allows efficient O(1) inserts, O(1) indexing and O(1) removal.
 ///
 /// See [crate level documentation](crate) for more information.
-#[derive(PartialEq, PartialOrd, Eq, Ord)]
 pub struct IndexMap<T> {
     data: Vec<OptionIndex<T>>,
     head: Option<usize>,
     len: usize,
+    // The insertion sequence number of the value in each slot, only present when insertion order
+    // is being tracked. Entries for free slots are stale and never read.
+    order: Option<Vec<u64>>,
+    next_seq: u64,
+    // The capacity below which `shrink_to_fit` and friends won't shrink `data`.
+    capacity_floor: usize,
 }
 
 impl<T> IndexMap<T> {
+    /// The maximum number of keys a map of `T` can ever hand out, and so also the maximum number
+    /// of values it can hold at once.
+    ///
+    /// Every key is backed by a slot in a single allocation, which holds either a value or the
+    /// key of the next free slot, so a slot takes up the size of the larger of `T` and `usize`,
+    /// plus a tag. Since an allocation can be at most [`isize::MAX`] bytes, this is `isize::MAX`
+    /// divided by the size of a slot. The largest key is one less than this. No key values are
+    /// reserved as sentinels.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// assert!(IndexMap::<u64>::MAX_KEYS < isize::MAX as usize / 8);
+    /// ```
+    pub const MAX_KEYS: usize = isize::MAX as usize / mem::size_of::<OptionIndex<T>>();
+
     /// Creates a new `IndexMap`.
     ///
     /// It initially has a capacity of 0, and won't allocate until first inserted into.
@@ -127,13 +178,17 @@ impl<T> IndexMap<T> {
             data: Vec::new(),
             head: None,
             len: 0,
+            order: None,
+            next_seq: 0,
+            capacity_floor: 0,
         }
     }
 
     /// Creates an empty `IndexMap` with the specified capacity.
     ///
     /// The map will be able to hold at least capacity elements without reallocating. If capacity
-    /// is 0, the map will not allocate.
+    /// is 0, the map will not allocate. Since a fresh map has no free slots, inserting capacity
+    /// elements into it hands out the keys `0..capacity`.
     ///
     /// # Examples
     /// ```
@@ -145,7 +200,135 @@ impl<T> IndexMap<T> {
             data: Vec::with_capacity(capacity),
             head: None,
             len: 0,
+            order: None,
+            next_seq: 0,
+            capacity_floor: 0,
+        }
+    }
+
+    /// Creates an empty `IndexMap` with the specified capacity, which
+    /// [`shrink_to_fit`](IndexMap::shrink_to_fit) and the other shrinking methods will never
+    /// reduce it below.
+    ///
+    /// The floor only limits shrinking: the map still grows past it as needed, and it has nothing
+    /// to do with how many elements are in the map. A floor of 0 is the same as
+    /// [`with_capacity`](IndexMap::with_capacity).
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let mut map = IndexMap::with_capacity_floor(16);
+    /// map.insert("a");
+    /// map.shrink_to_fit();
+    /// assert!(map.capacity() >= 16);
+    /// ```
+    pub fn with_capacity_floor(floor: usize) -> Self {
+        Self {
+            capacity_floor: floor,
+            ..Self::with_capacity(floor)
+        }
+    }
+
+    /// Creates an empty `IndexMap` which additionally records the order in which values are
+    /// inserted, so that they can be visited oldest first using
+    /// [`iter_insertion_order`](IndexMap::iter_insertion_order).
+    ///
+    /// This costs an extra `u64` per slot, and only applies to maps created through this
+    /// constructor.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let mut map: IndexMap<&str> = IndexMap::with_insertion_order_tracking();
+    /// ```
+    pub fn with_insertion_order_tracking() -> Self {
+        Self {
+            order: Some(Vec::new()),
+            ..Self::new()
+        }
+    }
+
+    /// Creates an `IndexMap` containing only the given value, returning the key for it along with
+    /// the map.
+    ///
+    /// Since the map is fresh, the returned key is always `0`.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let (key, map) = IndexMap::singleton("a");
+    /// assert_eq!(key, 0);
+    /// assert_eq!(map[key], "a");
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn singleton(value: T) -> (usize, Self) {
+        let mut map = Self::with_capacity(1);
+        let key = map.insert(value);
+        (key, map)
+    }
+
+    /// Creates a dense `IndexMap` with the keys `0..n`, where the value for each key is computed
+    /// by calling the closure with the key. Exactly `n` slots are allocated.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let map = IndexMap::from_fn(4, |key| key * key);
+    /// assert_eq!(map.len(), 4);
+    /// assert_eq!(map[3], 9);
+    /// ```
+    pub fn from_fn<F>(n: usize, mut f: F) -> Self
+    where
+        F: FnMut(usize) -> T,
+    {
+        let mut data = Vec::with_capacity(n);
+        data.extend((0..n).map(|key| OptionIndex::Some(f(key))));
+
+        Self {
+            data,
+            len: n,
+            ..Self::new()
+        }
+    }
+
+    /// Creates a dense `IndexMap` from the values of an iterator, which get the keys `0, 1, 2,
+    /// ...` in order. The memory is allocated fallibly, so that running out of memory returns an
+    /// error instead of aborting.
+    ///
+    /// Space for the lower bound of the iterator's [`size_hint`](Iterator::size_hint) is reserved
+    /// up front, and the map grows as usual after that.
+    ///
+    /// # Errors
+    /// Returns an error if the capacity overflows or the allocator reports a failure. Any values
+    /// already taken from the iterator are dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let map = IndexMap::try_from_iter(["a", "b", "c"].iter().copied()).unwrap();
+    /// assert_eq!(map.len(), 3);
+    /// assert_eq!(map[2], "c");
+    /// ```
+    pub fn try_from_iter<I>(iter: I) -> Result<Self, TryReserveError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let iter = iter.into_iter();
+        let mut data = Vec::new();
+        data.try_reserve(iter.size_hint().0)?;
+
+        for value in iter {
+            if data.len() == data.capacity() {
+                data.try_reserve(1)?;
+            }
+            data.push(OptionIndex::Some(value));
         }
+
+        Ok(Self {
+            len: data.len(),
+            data,
+            ..Self::new()
+        })
     }
 
     /// Returns the number of elements map can hold without reallocating.
@@ -160,6 +343,22 @@ impl<T> IndexMap<T> {
         self.data.capacity()
     }
 
+    /// Returns the number of bytes allocated for the map's slots, i.e. the
+    /// [`capacity()`](IndexMap::capacity) multiplied by the size of a single slot.
+    ///
+    /// Each slot is large enough to hold either a value or the index of the next free slot, so
+    /// this is at least `capacity() * size_of::<usize>()` even for small `T`.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let mut map: IndexMap<u64> = IndexMap::with_capacity(10);
+    /// assert!(map.capacity_bytes() >= 10 * std::mem::size_of::<u64>());
+    /// ```
+    pub fn capacity_bytes(&self) -> usize {
+        self.capacity() * mem::size_of::<OptionIndex<T>>()
+    }
+
     /// Returns the number of elements present in the map.
     ///
     /// # Examples
@@ -188,6 +387,22 @@ impl<T> IndexMap<T> {
         self.len() == 0
     }
 
+    /// Returns `true` if the map has no free slots, i.e. the keys are exactly `0..len()`.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert("a");
+    /// map.insert("b");
+    /// assert!(map.is_dense());
+    /// map.remove(a);
+    /// assert!(!map.is_dense());
+    /// ```
+    pub fn is_dense(&self) -> bool {
+        self.len == self.data.len()
+    }
+
     /// Clears the map, dropping all key-value pairs. Keeps the allocated memory for reuse.
     ///
     /// # Examples
@@ -201,8 +416,65 @@ impl<T> IndexMap<T> {
     /// assert!(map.is_empty());
     /// ```
     pub fn clear(&mut self) {
+        // There are no slots left, so there can't be any free ones.
+        self.head = None;
         self.len = 0;
-        self.data.clear()
+        self.data.clear();
+        if let Some(order) = &mut self.order {
+            order.clear();
+        }
+    }
+
+    /// Clears the map and inserts the given values, reusing the allocated memory. The values get
+    /// the keys `0, 1, 2, ...` in order.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let mut map = IndexMap::new();
+    ///
+    /// let a = map.insert("a");
+    /// map.insert("b");
+    /// map.remove(a);
+    ///
+    /// map.reset_to(vec!["c", "d"]);
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(map[0], "c");
+    /// assert_eq!(map[1], "d");
+    /// ```
+    pub fn reset_to<I: IntoIterator<Item = T>>(&mut self, values: I) {
+        self.clear();
+
+        let values = values.into_iter();
+        self.reserve(values.size_hint().0);
+        for value in values {
+            self.insert(value);
+        }
+    }
+
+    /// Clears the map, dropping the values strictly in ascending order of their keys. Keeps the
+    /// allocated memory for reuse.
+    ///
+    /// This is useful when the values' destructors have side effects which need to happen in a
+    /// defined order.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let mut map = IndexMap::new();
+    ///
+    /// map.insert("a");
+    /// map.clear_ordered();
+    ///
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn clear_ordered(&mut self) {
+        // The guard clears whatever is left once the loop is done, or if a destructor panics.
+        let guard = ClearOnDrop(self);
+
+        for slot in guard.0.data.iter_mut() {
+            drop(slot.take());
+        }
     }
 
     /// Reserves capacity for at least additional more elements to be inserted in the `IndexMap`
@@ -222,9 +494,77 @@ impl<T> IndexMap<T> {
         self.data.reserve(additional)
     }
 
+    /// Makes sure every key up to and including `max_key` has a slot, adding free slots to the end
+    /// of the map as needed. Inserting at any of these keys afterwards, for example with
+    /// [`insert_at_if_free`](IndexMap::insert_at_if_free), won't reallocate.
+    ///
+    /// The new free slots are also handed out by [`insert`](IndexMap::insert) before the map
+    /// grows any further.
+    ///
+    /// # Panics
+    /// Panics if the new capacity exceeds [`isize::MAX`] bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let mut map = IndexMap::new();
+    /// map.reserve_for_keys(9);
+    /// assert!(map.capacity() >= 10);
+    /// assert!(map.is_empty());
+    ///
+    /// let capacity = map.capacity();
+    /// map.insert_at_if_free(9, "a").unwrap();
+    /// map.insert_at_if_free(4, "b").unwrap();
+    /// assert_eq!(map.capacity(), capacity);
+    /// ```
+    pub fn reserve_for_keys(&mut self, max_key: usize) {
+        let len = max_key.checked_add(1).expect("capacity overflow");
+        self.grow_to(len);
+    }
+
+    /// Clones the map, giving the clone the capacity to hold at least `capacity` elements without
+    /// reallocating. If `capacity` is smaller than the storage the map needs, the clone gets just
+    /// enough to hold its contents.
+    ///
+    /// This avoids the extra reallocation of a [`clone`](Clone::clone) followed by a
+    /// [`reserve`](IndexMap::reserve).
+    ///
+    /// # Panics
+    /// Panics if the new capacity exceeds [`isize::MAX`] bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    ///
+    /// let clone = map.clone_with_capacity(10);
+    /// assert!(clone.capacity() >= 10);
+    /// assert_eq!(clone, map);
+    /// ```
+    pub fn clone_with_capacity(&self, capacity: usize) -> Self
+    where
+        T: Clone,
+    {
+        let mut data = Vec::with_capacity(capacity.max(self.capacity_floor).max(self.data.len()));
+        data.extend_from_slice(&self.data);
+
+        Self {
+            data,
+            head: self.head,
+            len: self.len,
+            order: self.order.clone(),
+            next_seq: self.next_seq,
+            capacity_floor: self.capacity_floor,
+        }
+    }
+
     /// Shrinks the capacity of the map as much as possible. It will drop down as much as possible
     /// while maintaining the internal rules and possibly leaving some space to keep keys valid.
     ///
+    /// The capacity is never reduced below the floor given to
+    /// [`with_capacity_floor`](IndexMap::with_capacity_floor).
+    ///
     /// # Examples
     /// ```
     /// use index_map::IndexMap;
@@ -236,77 +576,96 @@ impl<T> IndexMap<T> {
     /// assert!(map.capacity() >= 2);
     /// ```
     pub fn shrink_to_fit(&mut self) {
-        // This relies on the fact that `||` short-circuits. If `data` is empty, `head` *has* to be
-        // None, and so `data.last()` *cannot* be None.
-        if self.head.is_none() || self.data.last().unwrap().is_inner() {
-            self.data.shrink_to_fit();
-            return;
-        }
-
-        if self.is_empty() {
-            self.head = None;
-            self.data.clear();
-            self.data.shrink_to_fit();
-            return;
-        }
-
-        // random default value, the previous check makes sure there are elements, so the if
-        // condition has to be triggered.
-        let mut last = usize::MAX;
+        self.trim_free_tail();
+        self.data.shrink_to(self.capacity_floor);
+        self.shrink_order();
+    }
 
-        for (i, v) in self.data.iter().enumerate().rev() {
-            if v.is_inner() {
-                last = i;
-                break;
-            }
+    /// Removes the free slots after the largest key in use, without releasing any memory.
+    ///
+    /// Free slots between keys in use are left alone, and the capacity is unchanged. This is the
+    /// first step of [`shrink_to_fit`](IndexMap::shrink_to_fit), and is useful after removing
+    /// the entries with the largest keys, as it lets lookups past the new end bail out early and
+    /// makes the next [`insert`](IndexMap::insert) reuse a lower key.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let mut map = IndexMap::new();
+    /// for i in 0..10 {
+    ///     map.insert(i);
+    /// }
+    /// let capacity = map.capacity();
+    /// for key in 5..10 {
+    ///     map.remove(key);
+    /// }
+    /// map.remove(2);
+    ///
+    /// map.trim_tail();
+    /// assert_eq!(map.capacity(), capacity);
+    /// assert_eq!(map.insert(20), 2);
+    /// assert_eq!(map.insert(30), 5);
+    /// ```
+    pub fn trim_tail(&mut self) {
+        self.trim_free_tail();
+        if let Some(order) = &mut self.order {
+            order.truncate(self.data.len());
         }
+    }
 
-        assert_ne!(last, usize::MAX);
-
-        let mut head = self.head.unwrap();
-        // If head is more than last, it needs to be set in such a way that head points to an
-        // index which is not truncated
-        //                   ,-- head [ 4 ]   |   Key:
-        // .---.---.---.---.---.              |   *     = element
-        // | * | - | * | * | 1 |              |   -     = No Index
-        // '---'---'---'---'---'              |   <int> = Index
-        //               ^-- last [ 3 ]       |
-        // Take the above data. After shrinking, it would be erroneous for head to still point
-        // to 4, since it will be deleted.
-        let mut should_set_head = head > last;
-
-        while let OptionIndex::Index(next) = self.data[head] {
-            if next > last {
-                // We can't use clone because `T` is not required to be clone, so no bound is
-                // added. We can't use `OptionIndex::take`, since we need the index intact for
-                // the next loop.
-                self.data[head] = match self.data[next] {
-                    OptionIndex::Index(i) => OptionIndex::Index(i),
-                    OptionIndex::NoIndex => OptionIndex::NoIndex,
-                    OptionIndex::Some(_) => {
-                        unreachable!("encountered value while walking index list")
-                    }
-                };
-            }
-
-            if should_set_head && head < last {
-                self.head = Some(head);
-                should_set_head = false;
-            }
-            head = next;
-        }
+    /// Shrinks the capacity of the map like [`shrink_to_fit`](IndexMap::shrink_to_fit), but
+    /// leaves room for `slack` more slots past the largest key in use, so that the next few
+    /// inserts don't need to reallocate.
+    ///
+    /// # Panics
+    /// Panics if the new capacity exceeds [`isize::MAX`] bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let mut map = IndexMap::with_capacity(100);
+    /// map.insert("a");
+    /// let b = map.insert("b");
+    /// map.remove(b);
+    ///
+    /// map.shrink_with_slack(4);
+    /// assert!(map.capacity() >= 5);
+    /// assert!(map.capacity() < 100);
+    /// ```
+    pub fn shrink_with_slack(&mut self, slack: usize) {
+        self.trim_free_tail();
+        // `shrink_to` never grows the allocation, so make sure there is room for the slack first.
+        self.data.reserve_exact(slack);
+        self.data
+            .shrink_to(self.capacity_floor.max(self.data.len() + slack));
+        self.shrink_order();
+    }
 
-        // The only index not checked is `head`, replace `self.head` based on it.
-        if should_set_head {
-            self.head = if head < last { Some(head) } else { None };
+    /// Calls [`shrink_to_fit`](IndexMap::shrink_to_fit) only if fewer than
+    /// `load_factor * capacity()` slots are occupied.
+    ///
+    /// There is no default factor; pick one well below the map's typical fill (e.g. `0.25`) so
+    /// that a size oscillating around some value doesn't shrink and regrow the map each time.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    /// let mut map = IndexMap::with_capacity(100);
+    /// for i in 0..30 {
+    ///     map.insert(i);
+    /// }
+    ///
+    /// map.maybe_shrink(0.25);
+    /// assert!(map.capacity() >= 100);
+    ///
+    /// map.retain(|_, &mut v| v < 10);
+    /// map.maybe_shrink(0.25);
+    /// assert!(map.capacity() < 100);
+    /// ```
+    pub fn maybe_shrink(&mut self, load_factor: f32) {
+        if (self.len as f32) < load_factor * self.capacity() as f32 {
+            self.shrink_to_fit();
         }
-
-        self.data[head] = OptionIndex::NoIndex;
-
-        // Truncate expects length, not the index of last element
-        self.data.truncate(last + 1);
-
-        self.data.shrink_to_fit()
     }
 
     /// Returns `true` if the map contains a value for the specified key.
@@ -329,35 +688,30 @@ impl<T> IndexMap<T> {
         self.data[index].is_inner()
     }
 
-    /// Inserts a value into the map, returning the generated key, for it.
+    /// Returns `true` if the key has a slot in the map, but the slot is free.
+    ///
+    /// Keys past the end of the map are not free in this sense, even though inserting at them is
+    /// allowed; see [`is_key_allocatable`](IndexMap::is_key_allocatable).
     ///
     /// # Examples
     /// ```
     /// use index_map::IndexMap;
     ///
     /// let mut map = IndexMap::new();
-    /// assert_eq!(map.insert("a"), 0);
-    /// assert_eq!(map.is_empty(), false);
-    ///
+    /// let a = map.insert("a");
     /// let b = map.insert("b");
-    /// assert_eq!(map[b], "b");
+    /// map.remove(a);
+    ///
+    /// assert!(map.is_key_free(a));
+    /// assert!(!map.is_key_free(b));
+    /// assert!(!map.is_key_free(5));
     /// ```
-    pub fn insert(&mut self, value: T) -> usize {
-        // The operation can't fail (unless Vec panics internally) since the key is generated by us.
-        self.len += 1;
-
-        if let Some(head) = self.head {
-            self.head = self.data[head].take().into_index();
-            self.data[head] = OptionIndex::Some(value);
-            head
-        } else {
-            self.data.push(OptionIndex::Some(value));
-            self.data.len() - 1
-        }
+    pub fn is_key_free(&self, key: usize) -> bool {
+        self.data.get(key).is_some_and(|slot| !slot.is_inner())
     }
 
-    /// Removes a key from the map, returning the value at the key if the key was previously in
-    /// the map.
+    /// Returns `true` if a value can be inserted at the key without replacing another one, which
+    /// is the case for free slots and for keys past the end of the map.
     ///
     /// # Examples
     /// ```
@@ -365,93 +719,1402 @@ impl<T> IndexMap<T> {
     ///
     /// let mut map = IndexMap::new();
     /// let a = map.insert("a");
-    /// assert_eq!(map.remove(a), Some("a"));
-    /// assert_eq!(map.remove(a), None);
+    /// let b = map.insert("b");
+    /// map.remove(a);
+    ///
+    /// assert!(map.is_key_allocatable(a));
+    /// assert!(!map.is_key_allocatable(b));
+    /// assert!(map.is_key_allocatable(5));
     /// ```
-    pub fn remove(&mut self, index: usize) -> Option<T> {
-        if !self.data.get(index)?.is_inner() {
-            return None;
-        }
+    pub fn is_key_allocatable(&self, key: usize) -> bool {
+        !self.contains_key(key)
+    }
+
+    /// Returns `true` if the map contains a value equal to `value`.
+    ///
+    /// This scans the values in ascending order of keys, stopping at the first match, so it takes
+    /// time proportional to the size of the map.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    /// let b = map.insert("b");
+    /// map.remove(b);
+    ///
+    /// assert!(map.contains_value(&"a"));
+    /// assert!(!map.contains_value(&"b"));
+    /// ```
+    pub fn contains_value(&self, value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.values().any(|v| v == value)
+    }
+
+    /// Inserts a value into the map, returning the generated key, for it.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// assert_eq!(map.insert("a"), 0);
+    /// assert_eq!(map.is_empty(), false);
+    ///
+    /// let b = map.insert("b");
+    /// assert_eq!(map[b], "b");
+    /// ```
+    pub fn insert(&mut self, value: T) -> usize {
+        // The operation can't fail (unless Vec panics internally) since the key is generated by us.
+        self.len += 1;
+
+        let key = if let Some(head) = self.head {
+            self.head = self.data[head].take().into_index();
+            self.data[head] = OptionIndex::Some(value);
+            head
+        } else {
+            debug_assert!(self.data.len() < Self::MAX_KEYS, "map is out of keys");
+            self.data.push(OptionIndex::Some(value));
+            self.data.len() - 1
+        };
+
+        self.record_insertion(key);
+        key
+    }
+
+    /// Inserts the value returned by `f` into the map, returning the generated key along with a
+    /// mutable reference to the value.
+    ///
+    /// If `f` fails, its error is returned and the map is left unchanged. In particular, no key is
+    /// used up, so the next insert gets the key this one would have.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    ///
+    /// let res: Result<_, ()> = map.try_insert_mut(|| Ok(1));
+    /// let (key, val) = res.unwrap();
+    /// *val += 1;
+    /// assert_eq!(map[key], 2);
+    ///
+    /// assert_eq!(map.try_insert_mut(|| "12a".parse::<i32>()).is_err(), true);
+    /// assert_eq!(map.len(), 1);
+    /// assert_eq!(map.insert(3), 1);
+    /// ```
+    pub fn try_insert_mut<E, F>(&mut self, f: F) -> Result<(usize, &mut T), E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        let value = f()?;
+        // This is the same key that `insert` would generate.
+        let key = self.head.unwrap_or(self.data.len());
+        Ok((key, self.insert_vacant(key, value)))
+    }
+
+    /// Inserts a value at the given key, returning the value previously at the key, if any.
+    ///
+    /// Unlike [`insert`](IndexMap::insert), this uses exactly the given key, which makes it
+    /// possible to restore entries at their original keys:
+    ///
+    /// - If the key is occupied, its value is replaced and the old one returned.
+    /// - If the key is a free slot, it is unlinked from the free list. This is O(1) if it is the
+    ///   next key `insert` would have used, and otherwise walks the free list.
+    /// - If the key is past the end of the map, the map is padded with free slots up to it, which
+    ///   will be handed out by subsequent inserts.
+    ///
+    /// # Panics
+    /// Panics if the new capacity exceeds [`isize::MAX`] bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// assert_eq!(map.insert_at(2, "a"), None);
+    /// assert_eq!(map.insert_at(2, "b"), Some("a"));
+    /// assert_eq!(map[2], "b");
+    ///
+    /// // The padding before the key is reused.
+    /// assert_eq!(map.insert_at(0, "c"), None);
+    /// assert_eq!(map.insert("d"), 1);
+    /// ```
+    pub fn insert_at(&mut self, key: usize, value: T) -> Option<T> {
+        if let Some(old) = self.get_mut(key) {
+            return Some(mem::replace(old, value));
+        }
+
+        self.insert_vacant(key, value);
+        None
+    }
+
+    /// Inserts a value at the given key, returning the value previously at the key, if any.
+    ///
+    /// If the key is past the end of the map, the map is padded with free slots up to it. The
+    /// memory for that is reserved fallibly, as is the memory for recording the insertion order
+    /// if the map [tracks it](IndexMap::with_insertion_order_tracking): if an allocation fails,
+    /// the map is left completely unchanged and the key and value are handed back.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// assert_eq!(map.try_insert_at(2, "a"), Ok(None));
+    /// assert_eq!(map.try_insert_at(2, "b"), Ok(Some("a")));
+    /// assert_eq!(map[2], "b");
+    ///
+    /// assert_eq!(map.try_insert_at(usize::MAX / 2, "c"), Err((usize::MAX / 2, "c")));
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn try_insert_at(&mut self, key: usize, value: T) -> Result<Option<T>, (usize, T)> {
+        if let Some(old) = self.get_mut(key) {
+            return Ok(Some(mem::replace(old, value)));
+        }
+
+        if let Some(order) = &mut self.order {
+            // `order` may be shorter than `data`, so it can need to grow even for a key within the
+            // map.
+            let additional = key.saturating_add(1).saturating_sub(order.len());
+            if order.try_reserve(additional).is_err() {
+                return Err((key, value));
+            }
+        }
+
+        if key >= self.data.len() {
+            let additional = match (key - self.data.len()).checked_add(1) {
+                Some(additional) => additional,
+                None => return Err((key, value)),
+            };
+
+            if self.data.try_reserve(additional).is_err() {
+                return Err((key, value));
+            }
+        }
+
+        self.insert_vacant(key, value);
+        Ok(None)
+    }
+
+    /// Inserts every value at its given key, replacing and dropping any value already at the key.
+    /// If a key appears more than once, the last value for it wins.
+    ///
+    /// This is meant for bulk restores: the map is grown only once, to fit the largest key, and
+    /// the free list is rebuilt once at the end, so the whole batch takes time proportional to the
+    /// number of pairs plus the number of slots.
+    ///
+    /// # Panics
+    /// Panics if the new capacity exceeds [`isize::MAX`] bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert_batch_at(vec![(3, "a"), (0, "b"), (5, "c")]);
+    /// assert_eq!(map.len(), 3);
+    /// assert_eq!(map[3], "a");
+    ///
+    /// // The gaps are free slots, which get reused first.
+    /// assert_eq!(map.insert("d"), 1);
+    /// ```
+    pub fn insert_batch_at<I>(&mut self, pairs: I)
+    where
+        I: IntoIterator<Item = (usize, T)>,
+    {
+        let pairs: Vec<_> = pairs.into_iter().collect();
+        let max_key = match pairs.iter().map(|&(key, _)| key).max() {
+            Some(max_key) => max_key,
+            None => return,
+        };
+
+        let len = max_key.checked_add(1).expect("capacity overflow");
+        if self.data.len() < len {
+            self.data.resize_with(len, || OptionIndex::NoIndex);
+        }
+
+        // Filling a free slot breaks the free list, so it is rebuilt once everything is in place,
+        // or if dropping a replaced value panics.
+        let guard = RebuildOnDrop(self);
+        let map = &mut *guard.0;
+        for (key, value) in pairs {
+            let old = mem::replace(&mut map.data[key], OptionIndex::Some(value));
+            map.record_insertion(key);
+            drop(old);
+        }
+    }
+
+    /// Inserts a value at the given key, but only if the key is free. If the key is occupied, the
+    /// map is left unchanged and the value is handed back.
+    ///
+    /// If the key is past the end of the map, the map is padded with free slots up to it.
+    ///
+    /// # Panics
+    /// Panics if the new capacity exceeds [`isize::MAX`] bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// assert_eq!(map.insert_at_if_free(2, "a"), Ok(()));
+    /// assert_eq!(map.insert_at_if_free(2, "b"), Err("b"));
+    /// assert_eq!(map[2], "a");
+    ///
+    /// assert_eq!(map.insert_at_if_free(0, "c"), Ok(()));
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn insert_at_if_free(&mut self, key: usize, value: T) -> Result<(), T> {
+        if self.contains_key(key) {
+            return Err(value);
+        }
+
+        self.insert_vacant(key, value);
+        Ok(())
+    }
+
+    /// Inserts a value at the given key, but only if the key is free, like
+    /// [`insert_at_if_free`](IndexMap::insert_at_if_free). If the key is occupied, the map is left
+    /// unchanged and an error holding the key and the rejected value is returned.
+    ///
+    /// If the key is past the end of the map, the map is padded with free slots up to it.
+    ///
+    /// # Panics
+    /// Panics if the new capacity exceeds [`isize::MAX`] bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::{IndexMap, OccupiedError};
+    ///
+    /// let mut map = IndexMap::new();
+    /// assert_eq!(map.try_insert(3, "a"), Ok(()));
+    /// assert_eq!(map.try_insert(3, "b"), Err(OccupiedError { key: 3, value: "b" }));
+    /// assert_eq!(map[3], "a");
+    /// ```
+    pub fn try_insert(&mut self, key: usize, value: T) -> Result<(), OccupiedError<T>> {
+        self.insert_at_if_free(key, value)
+            .map_err(|value| OccupiedError { key, value })
+    }
+
+    /// Clones and inserts all the values in the slice, in order. Keys are generated for them
+    /// exactly as if each was passed to [`insert`](IndexMap::insert), so free slots get reused
+    /// first.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    /// map.extend_from_slice(&["b", "c"]);
+    ///
+    /// assert_eq!(map.len(), 3);
+    /// assert_eq!(map[2], "c");
+    /// ```
+    pub fn extend_from_slice(&mut self, slice: &[T])
+    where
+        T: Clone,
+    {
+        self.extend(slice.iter().cloned());
+    }
+
+    /// Moves all the values of `other` into `self`, leaving `other` empty.
+    ///
+    /// The values are moved, not cloned, in ascending order of their keys in `other`, and get new
+    /// keys exactly as if each was passed to [`insert`](IndexMap::insert). Space for all of them
+    /// is reserved up front.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut a = IndexMap::new();
+    /// a.insert("a");
+    /// let mut b = IndexMap::new();
+    /// b.insert("b");
+    /// b.insert("c");
+    ///
+    /// a.append(&mut b);
+    /// assert_eq!(a.len(), 3);
+    /// assert_eq!(a[2], "c");
+    /// assert!(b.is_empty());
+    /// ```
+    pub fn append(&mut self, other: &mut Self) {
+        // Only the values which don't fit in the free slots need new space.
+        let free = self.data.len() - self.len;
+        self.reserve(other.len().saturating_sub(free));
+
+        for (_, value) in other.drain() {
+            self.insert(value);
+        }
+    }
+
+    /// Moves all the values of `other` into `self` at the same keys they had in `other`, leaving
+    /// `other` empty.
+    ///
+    /// If any key is present in both maps, neither map is changed, and the smallest such key is
+    /// returned in the error.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::{IndexMap, KeyCollision};
+    ///
+    /// let mut a = IndexMap::new();
+    /// a.insert("a");
+    /// let mut b = IndexMap::new();
+    /// b.insert("x");
+    /// b.insert("b");
+    /// b.remove(0);
+    ///
+    /// assert_eq!(a.append_preserving(&mut b), Ok(()));
+    /// assert_eq!(a[0], "a");
+    /// assert_eq!(a[1], "b");
+    /// assert!(b.is_empty());
+    ///
+    /// let mut c = IndexMap::new();
+    /// c.insert("c");
+    /// assert_eq!(a.append_preserving(&mut c), Err(KeyCollision { key: 0 }));
+    /// assert_eq!(c.len(), 1);
+    /// ```
+    pub fn append_preserving(&mut self, other: &mut Self) -> Result<(), KeyCollision> {
+        if let Some(key) = other.keys().find(|&key| self.contains_key(key)) {
+            return Err(KeyCollision { key });
+        }
+
+        if self.data.len() < other.data.len() {
+            self.data
+                .resize_with(other.data.len(), || OptionIndex::NoIndex);
+        }
+
+        for (key, value) in other.drain() {
+            self.data[key] = OptionIndex::Some(value);
+            self.record_insertion(key);
+        }
+
+        // Many of the free slots may have been filled, so relinking them all at once is cheaper
+        // than unlinking the filled ones one by one.
+        self.rebuild_free_list();
+        Ok(())
+    }
+
+    /// Fills every free key in `0..new_len` with a clone of `value`, leaving existing entries
+    /// alone.
+    ///
+    /// Note that, unlike [`Vec::resize`], this never removes anything. If `new_len` is less than
+    /// the largest key in the map, the entries with keys past `new_len` are kept as they are.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    /// let b = map.insert("b");
+    /// map.insert("c");
+    /// map.remove(b);
+    ///
+    /// map.resize(4, "z");
+    /// assert_eq!(map.values().copied().collect::<Vec<_>>(), ["a", "z", "c", "z"]);
+    ///
+    /// map.resize(1, "y");
+    /// assert_eq!(map.len(), 4);
+    /// ```
+    pub fn resize(&mut self, new_len: usize, value: T)
+    where
+        T: Clone,
+    {
+        // Free slots are overwritten without unlinking them, so the free list is redone from
+        // whichever slots are still free once done, or if `clone` panics partway through.
+        let guard = RebuildOnDrop(self);
+        let map = &mut *guard.0;
+        map.data.reserve(new_len.saturating_sub(map.data.len()));
+
+        for i in 0..new_len.min(map.data.len()) {
+            if !map.data[i].is_inner() {
+                map.data[i] = OptionIndex::Some(value.clone());
+                map.record_insertion(i);
+            }
+        }
+
+        while map.data.len() < new_len {
+            map.data.push(OptionIndex::Some(value.clone()));
+            map.record_insertion(map.data.len() - 1);
+        }
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key was previously in
+    /// the map.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert("a");
+    /// assert_eq!(map.remove(a), Some("a"));
+    /// assert_eq!(map.remove(a), None);
+    /// ```
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if !self.data.get(index)?.is_inner() {
+            return None;
+        }
 
         let val = self.data.get_mut(index)?.take().into_inner()?;
 
-        if let Some(head) = self.head {
-            self.data[index] = OptionIndex::Index(head);
-        } else {
-            self.data[index] = OptionIndex::NoIndex;
+        self.link_free(index);
+        self.len -= 1;
+
+        Some(val)
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key was previously in
+    /// the map. If the key was the largest one in use, the free slots at the end of the map are
+    /// trimmed off as well, so they don't build up.
+    ///
+    /// Removing the last slot on its own is O(1). Otherwise the free slots after the key are
+    /// scanned to find out whether it was the largest one in use, and if so they are trimmed like
+    /// in [`shrink_to_fit`](IndexMap::shrink_to_fit), which walks the free list. Unlike
+    /// `shrink_to_fit`, the allocated memory is kept.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert("a");
+    /// let b = map.insert("b");
+    /// let c = map.insert("c");
+    ///
+    /// map.remove(b);
+    /// assert_eq!(map.remove_trim(c), Some("c"));
+    /// // Both `b` and `c` were trimmed off, so the next key is `b` again.
+    /// assert_eq!(map.insert("d"), b);
+    /// assert_eq!(map.insert("e"), c);
+    /// assert_eq!(map.remove_trim(a), Some("a"));
+    /// ```
+    pub fn remove_trim(&mut self, index: usize) -> Option<T> {
+        if !self.data.get(index).is_some_and(OptionIndex::is_inner) {
+            return None;
+        }
+
+        let val = if index + 1 == self.data.len() {
+            // The slot is occupied, so it isn't part of the free list and can simply be dropped.
+            let val = self.data.pop()?.into_inner()?;
+            self.len -= 1;
+            val
+        } else if self.data[index + 1..].iter().any(OptionIndex::is_inner) {
+            return self.remove(index);
+        } else {
+            self.remove(index)?
+        };
+
+        // Every slot after `index` is free now, so they can all go.
+        self.trim_free_tail();
+        if let Some(order) = &mut self.order {
+            order.truncate(self.data.len());
+        }
+
+        Some(val)
+    }
+
+    /// Removes the entry with the smallest key, and moves the entry with the largest key into its
+    /// place, keeping the occupied keys packed towards the front.
+    ///
+    /// Returns the removed key and value, along with the key the moved entry had before, if an
+    /// entry was moved. The moved entry can now be found at the removed key.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    /// map.insert("b");
+    /// map.insert("c");
+    ///
+    /// assert_eq!(map.pop_front_swap(), Some((0, "a", Some(2))));
+    /// assert_eq!(map[0], "c");
+    /// assert_eq!(map.pop_front_swap(), Some((0, "c", Some(1))));
+    /// assert_eq!(map.pop_front_swap(), Some((0, "b", None)));
+    /// assert_eq!(map.pop_front_swap(), None);
+    /// ```
+    pub fn pop_front_swap(&mut self) -> Option<(usize, T, Option<usize>)> {
+        let (first, last) = self.key_bounds()?;
+        let value = self.data[first].take().into_inner()?;
+
+        if first != last {
+            self.data.swap(first, last);
+            if let Some(order) = &mut self.order {
+                order.swap(first, last);
+            }
+        }
+        self.len -= 1;
+
+        // `last` was occupied, so it isn't part of the free list, and can simply be dropped if it
+        // is at the end.
+        if last + 1 == self.data.len() {
+            self.data.pop();
+            if let Some(order) = &mut self.order {
+                order.truncate(self.data.len());
+            }
+        } else {
+            self.link_free(last);
+        }
+
+        let moved = if first != last { Some(last) } else { None };
+        Some((first, value, moved))
+    }
+
+    /// Removes a key from the map, returning the key and value if the key was previously in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert("a");
+    /// assert_eq!(map.remove_entry(a), Some((0, "a")));
+    /// assert_eq!(map.remove(a), None);
+    /// ```
+    pub fn remove_entry(&mut self, index: usize) -> Option<(usize, T)> {
+        Some((index, self.remove(index)?))
+    }
+
+    /// Removes every entry whose key falls within `range`, dropping the values, and returns the
+    /// number of entries removed. The freed keys become available for reuse.
+    ///
+    /// Parts of the range past the largest key the map has ever used are ignored.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// for i in 0..6 {
+    ///     map.insert(i);
+    /// }
+    /// map.remove(2);
+    ///
+    /// assert_eq!(map.remove_range(1..4), 2);
+    /// assert_eq!(map.keys().collect::<Vec<_>>(), [0, 4, 5]);
+    /// assert_eq!(map.remove_range(5..), 1);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) -> usize {
+        let mut removed = 0;
+
+        for i in self.key_range(range) {
+            if self.data[i].is_inner() {
+                let val = self.data[i].take();
+                self.link_free(i);
+                self.len -= 1;
+                removed += 1;
+                drop(val);
+            }
+        }
+
+        removed
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// The key can either be a `usize` or a [`Handle`].
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    /// assert_eq!(map.get(0), Some(&"a"));
+    /// assert_eq!(map.get(1), None);
+    /// ```
+    pub fn get<K: Key>(&self, key: K) -> Option<&T> {
+        self.data.get(key.into_key())?.as_ref().into_inner()
+    }
+
+    /// Returns the key-value pair corresponding to the key.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    /// assert_eq!(map.get_key_value(0), Some((0, &"a")));
+    /// assert_eq!(map.get_key_value(1), None);
+    /// ```
+    pub fn get_key_value(&self, index: usize) -> Option<(usize, &T)> {
+        Some((index, self.get(index)?))
+    }
+
+    /// Returns a clone of the value corresponding to the key.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(String::from("a"));
+    /// assert_eq!(map.get_cloned(0), Some(String::from("a")));
+    /// assert_eq!(map.get_cloned(1), None);
+    /// ```
+    pub fn get_cloned(&self, index: usize) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.get(index).cloned()
+    }
+
+    /// Returns the key along with a clone of the value corresponding to the key.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(String::from("a"));
+    /// assert_eq!(map.get_key_value_cloned(0), Some((0, String::from("a"))));
+    /// assert_eq!(map.get_key_value_cloned(1), None);
+    /// ```
+    pub fn get_key_value_cloned(&self, index: usize) -> Option<(usize, T)>
+    where
+        T: Clone,
+    {
+        Some((index, self.get_cloned(index)?))
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    ///
+    /// The key can either be a `usize` or a [`Handle`].
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert("a");
+    /// if let Some(x) = map.get_mut(a) {
+    ///     *x = "b";
+    /// }
+    /// assert_eq!(map[a], "b");
+    /// ```
+    pub fn get_mut<K: Key>(&mut self, key: K) -> Option<&mut T> {
+        match self.data.get_mut(key.into_key())? {
+            OptionIndex::Some(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key, first inserting
+    /// `default` at exactly that key if it isn't present.
+    ///
+    /// If the key is past the end of the map, the map is padded with free slots up to it. This is
+    /// a shorthand for `map.entry(key).or_insert(default)`.
+    ///
+    /// # Panics
+    /// Panics if the new capacity exceeds [`isize::MAX`] bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// *map.get_mut_or_insert_at(3, 0) += 1;
+    /// *map.get_mut_or_insert_at(3, 0) += 1;
+    /// assert_eq!(map[3], 2);
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn get_mut_or_insert_at(&mut self, key: usize, default: T) -> &mut T {
+        self.entry(key).or_insert(default)
+    }
+
+    /// Calls the closure with the key and a mutable reference to the value corresponding to the
+    /// key, returning its result, or `None` if the key isn't present.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert(String::from("a"));
+    ///
+    /// let len = map.get_key_value_mut_or(a, |key, val| {
+    ///     val.push_str(&key.to_string());
+    ///     val.len()
+    /// });
+    /// assert_eq!(len, Some(2));
+    /// assert_eq!(map[a], "a0");
+    /// assert_eq!(map.get_key_value_mut_or(1, |_, val| val.len()), None);
+    /// ```
+    pub fn get_key_value_mut_or<R, F>(&mut self, index: usize, f: F) -> Option<R>
+    where
+        F: FnOnce(usize, &mut T) -> R,
+    {
+        Some(f(index, self.get_mut(index)?))
+    }
+
+    /// Returns mutable references to the values of several keys at once, reporting for each key
+    /// whether it could be resolved.
+    ///
+    /// The keys may be plain `usize` keys or [`Handle`]s, and may be given in any order. A key
+    /// which is not present gets [`DisjointError::Absent`]. If a key appears more than once, only
+    /// its first occurrence gets the reference, and every later one gets
+    /// [`DisjointError::Duplicate`], so that no value is borrowed twice.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::{DisjointError, IndexMap};
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert(1);
+    /// let b = map.insert(2);
+    ///
+    /// let [x, y, z, w] = map.get_disjoint_mut_report([b, a, 5, b]);
+    /// assert_eq!(z, Err(DisjointError::Absent));
+    /// assert_eq!(w, Err(DisjointError::Duplicate));
+    ///
+    /// let (x, y) = (x.unwrap(), y.unwrap());
+    /// std::mem::swap(x, y);
+    /// assert_eq!(map[a], 2);
+    /// assert_eq!(map[b], 1);
+    /// ```
+    pub fn get_disjoint_mut_report<K: Key, const N: usize>(
+        &mut self,
+        keys: [K; N],
+    ) -> [Result<&mut T, DisjointError>; N] {
+        let keys = keys.map(Key::into_key);
+        let errors: [Option<DisjointError>; N] = core::array::from_fn(|i| {
+            if keys[..i].contains(&keys[i]) {
+                Some(DisjointError::Duplicate)
+            } else if !self.contains_key(keys[i]) {
+                Some(DisjointError::Absent)
+            } else {
+                None
+            }
+        });
+
+        // Visit the requested slots in ascending order, so that they can all be split off a
+        // single pass over `data`.
+        let mut sorted: [usize; N] = core::array::from_fn(|i| i);
+        sorted.sort_unstable_by_key(|&i| keys[i]);
+
+        let mut values: [Option<&mut T>; N] = core::array::from_fn(|_| None);
+        let mut slots = self.data.iter_mut().enumerate();
+        for i in sorted {
+            if errors[i].is_some() {
+                continue;
+            }
+
+            if let Some((_, OptionIndex::Some(val))) = slots.find(|(key, _)| *key == keys[i]) {
+                values[i] = Some(val);
+            }
+        }
+
+        core::array::from_fn(|i| match values[i].take() {
+            Some(val) => Ok(val),
+            None => Err(errors[i].expect("resolvable key was not found")),
+        })
+    }
+
+    /// Returns mutable references to the values of several different keys at once, or `None` if
+    /// any key isn't present or any two keys are the same.
+    ///
+    /// The keys may be plain `usize` keys or [`Handle`]s, and may be given in any order. To find
+    /// out which key couldn't be resolved, use
+    /// [`get_disjoint_mut_report`](IndexMap::get_disjoint_mut_report).
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert(1);
+    /// let b = map.insert(2);
+    /// let c = map.insert(3);
+    ///
+    /// if let Some([x, y, z]) = map.get_disjoint_mut([c, a, b]) {
+    ///     std::mem::swap(x, y);
+    ///     *z *= 10;
+    /// }
+    /// assert_eq!(map.values().copied().collect::<Vec<_>>(), [3, 20, 1]);
+    ///
+    /// assert!(map.get_disjoint_mut([a, a]).is_none());
+    /// assert!(map.get_disjoint_mut([a, 7]).is_none());
+    /// ```
+    pub fn get_disjoint_mut<K: Key, const N: usize>(
+        &mut self,
+        keys: [K; N],
+    ) -> Option<[&mut T; N]> {
+        let values = self.get_disjoint_mut_report(keys);
+        if values.iter().any(Result::is_err) {
+            return None;
+        }
+        Some(values.map(|val| val.expect("every key was resolved")))
+    }
+
+    /// Returns mutable references to the values of two different keys, or `None` if either key
+    /// isn't present or both keys are the same.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert(1);
+    /// let b = map.insert(2);
+    ///
+    /// if let Some((x, y)) = map.get2_mut(a, b) {
+    ///     std::mem::swap(x, y);
+    /// }
+    /// assert_eq!(map[a], 2);
+    /// assert!(map.get2_mut(a, a).is_none());
+    /// ```
+    pub fn get2_mut<K: Key>(&mut self, a: K, b: K) -> Option<(&mut T, &mut T)> {
+        let [a, b] = self.get_disjoint_mut_report([a, b]);
+        Some((a.ok()?, b.ok()?))
+    }
+
+    /// Returns mutable references to the values of three different keys, or `None` if any key
+    /// isn't present or any two keys are the same.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert(1);
+    /// let b = map.insert(2);
+    /// let c = map.insert(0);
+    ///
+    /// if let Some((x, y, z)) = map.get3_mut(a, b, c) {
+    ///     *z = *x + *y;
+    /// }
+    /// assert_eq!(map[c], 3);
+    /// assert!(map.get3_mut(a, b, a).is_none());
+    /// ```
+    pub fn get3_mut<K: Key>(&mut self, a: K, b: K, c: K) -> Option<(&mut T, &mut T, &mut T)> {
+        let [a, b, c] = self.get_disjoint_mut_report([a, b, c]);
+        Some((a.ok()?, b.ok()?, c.ok()?))
+    }
+
+    /// Swaps the values at two keys, returning `false` without changing anything if either key
+    /// isn't present.
+    ///
+    /// The keys themselves stay the same, and swapping a key with itself does nothing but still
+    /// returns `true` if it is present.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert("a");
+    /// let b = map.insert("b");
+    ///
+    /// assert!(map.swap(a, b));
+    /// assert_eq!(map[a], "b");
+    /// assert_eq!(map[b], "a");
+    ///
+    /// assert!(!map.swap(a, 5));
+    /// ```
+    pub fn swap(&mut self, a: usize, b: usize) -> bool {
+        if a == b {
+            return self.contains_key(a);
+        }
+
+        match self.get2_mut(a, b) {
+            Some((a, b)) => {
+                mem::swap(a, b);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Calls `f` with the key and a mutable reference to the value of each key in `keys`, one
+    /// after another. Keys which aren't present are skipped.
+    ///
+    /// Unlike [`get_disjoint_mut_report`](IndexMap::get_disjoint_mut_report), the keys don't
+    /// need to be distinct, since only one value is borrowed at a time; a key that is listed
+    /// twice gets `f` applied twice.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert(1);
+    /// let b = map.insert(2);
+    ///
+    /// map.update_many(&[a, b, a, 7], |_, v| *v *= 10);
+    /// assert_eq!(map[a], 100);
+    /// assert_eq!(map[b], 20);
+    /// ```
+    pub fn update_many<F>(&mut self, keys: &[usize], mut f: F)
+    where
+        F: FnMut(usize, &mut T),
+    {
+        for &key in keys {
+            if let Some(val) = self.get_mut(key) {
+                f(key, val);
+            }
+        }
+    }
+
+    /// Returns the entry with the smallest key, or `None` if the map is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// assert_eq!(map.first_key_value(), None);
+    ///
+    /// let a = map.insert("a");
+    /// map.insert("b");
+    /// map.remove(a);
+    /// assert_eq!(map.first_key_value(), Some((1, &"b")));
+    /// ```
+    pub fn first_key_value(&self) -> Option<(usize, &T)> {
+        self.iter().next()
+    }
+
+    /// Returns the entry with the largest key, or `None` if the map is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// assert_eq!(map.last_key_value(), None);
+    ///
+    /// map.insert("a");
+    /// let b = map.insert("b");
+    /// map.remove(b);
+    /// assert_eq!(map.last_key_value(), Some((0, &"a")));
+    /// ```
+    pub fn last_key_value(&self) -> Option<(usize, &T)> {
+        self.iter().next_back()
+    }
+
+    /// Returns the entry with the smallest key, with a mutable reference to the value.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert(1);
+    /// map.insert(2);
+    /// map.remove(a);
+    ///
+    /// if let Some((key, val)) = map.first_key_value_mut() {
+    ///     assert_eq!(key, 1);
+    ///     *val *= 10;
+    /// }
+    /// assert_eq!(map[1], 20);
+    /// ```
+    pub fn first_key_value_mut(&mut self) -> Option<(usize, &mut T)> {
+        self.iter_mut().next()
+    }
+
+    /// Returns the smallest and the largest keys present in the map, or `None` if it is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// assert_eq!(map.key_bounds(), None);
+    ///
+    /// let a = map.insert("a");
+    /// map.insert("b");
+    /// map.insert("c");
+    /// map.remove(a);
+    /// assert_eq!(map.key_bounds(), Some((1, 2)));
+    /// ```
+    pub fn key_bounds(&self) -> Option<(usize, usize)> {
+        let min = self.data.iter().position(OptionIndex::is_inner)?;
+        let max = self.data.iter().rposition(OptionIndex::is_inner)?;
+        Some((min, max))
+    }
+
+    /// Returns the number of keys within `range` that are present in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// for i in 0..10 {
+    ///     map.insert(i);
+    /// }
+    /// map.remove(3);
+    ///
+    /// assert_eq!(map.count_range(2..5), 2);
+    /// assert_eq!(map.count_range(8..), 2);
+    /// assert_eq!(map.count_range(..), 9);
+    /// ```
+    pub fn count_range<R: RangeBounds<usize>>(&self, range: R) -> usize {
+        self.data[self.key_range(range)]
+            .iter()
+            .filter(|slot| slot.is_inner())
+            .count()
+    }
+
+    /// Applies the function to the entries in ascending order of keys, and returns the first
+    /// non-`None` result.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    /// map.insert("12");
+    /// map.insert("34");
+    ///
+    /// assert_eq!(map.find_map_value(|_, v| v.parse::<u32>().ok()), Some(12));
+    /// assert_eq!(map.find_map_value(|k, _| if k > 5 { Some(k) } else { None }), None);
+    /// ```
+    pub fn find_map_value<B, F>(&self, mut f: F) -> Option<B>
+    where
+        F: FnMut(usize, &T) -> Option<B>,
+    {
+        self.iter().find_map(|(key, val)| f(key, val))
+    }
+
+    /// Returns the smallest key whose value matches the predicate, or `None` if there is none.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("apple");
+    /// map.insert("banana");
+    /// map.insert("blueberry");
+    ///
+    /// assert_eq!(map.find_key(|v| v.starts_with('b')), Some(1));
+    /// assert_eq!(map.find_key(|v| v.is_empty()), None);
+    /// ```
+    pub fn find_key<P>(&self, mut predicate: P) -> Option<usize>
+    where
+        P: FnMut(&T) -> bool,
+    {
+        self.iter()
+            .find(|(_, val)| predicate(val))
+            .map(|(key, _)| key)
+    }
+
+    /// Clears `buf` and fills it with all the keys in ascending order, reusing its allocation.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    /// map.insert("b");
+    /// map.insert("c");
+    /// map.remove(1);
+    ///
+    /// let mut keys = vec![7, 8, 9];
+    /// map.collect_keys_into(&mut keys);
+    /// assert_eq!(keys, [0, 2]);
+    /// ```
+    pub fn collect_keys_into(&self, buf: &mut Vec<usize>) {
+        buf.clear();
+        buf.extend(self.keys());
+    }
+
+    /// Clears `buf` and fills it with clones of all the values in ascending order of their keys,
+    /// reusing its allocation.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert("a");
+    /// map.insert("b");
+    /// map.insert("c");
+    /// map.remove(1);
+    ///
+    /// let mut values = Vec::new();
+    /// map.collect_values_into(&mut values);
+    /// assert_eq!(values, ["a", "c"]);
+    /// ```
+    pub fn collect_values_into(&self, buf: &mut Vec<T>)
+    where
+        T: Clone,
+    {
+        buf.clear();
+        buf.extend(self.values().cloned());
+    }
+
+    /// Retains only the elements specified by the predicate, returning the number of elements
+    /// removed.
+    ///
+    /// In other words, remove all pairs `(k, v)` such that `f(k, &mut v)` returns `false`.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// for i in 0..6 {
+    ///     map.insert(i*2);
+    /// }
+    /// assert_eq!(map.retain(|k, _| k % 2 == 0), 3);
+    /// assert_eq!(map.len(), 3);
+    /// ```
+    pub fn retain<P>(&mut self, mut predicate: P) -> usize
+    where
+        P: FnMut(usize, &mut T) -> bool,
+    {
+        let len = self.len;
+
+        // Cannot use `self.iter_mut` as we need the pointer to the `OptionIndex` and not the value
+        // contained in it.
+        for (i, v) in self.data.iter_mut().enumerate() {
+            if let OptionIndex::Some(val) = v {
+                if !predicate(i, val) {
+                    *v = if let Some(head) = self.head {
+                        OptionIndex::Index(head)
+                    } else {
+                        OptionIndex::NoIndex
+                    };
+
+                    self.len -= 1;
+                    self.head = Some(i)
+                }
+            }
         }
 
-        self.head = Some(index);
-        self.len -= 1;
-
-        Some(val)
+        len - self.len
     }
 
-    /// Removes a key from the map, returning the key and value if the key was previously in the map.
+    /// Retains only the elements specified by the predicate, which only gets a shared reference to
+    /// each value.
+    ///
+    /// In other words, remove all pairs `(k, v)` such that `f(k, &v)` returns `false`. This is
+    /// otherwise identical to [`retain`](IndexMap::retain).
     ///
     /// # Examples
     /// ```
     /// use index_map::IndexMap;
     ///
     /// let mut map = IndexMap::new();
-    /// let a = map.insert("a");
-    /// assert_eq!(map.remove_entry(a), Some((0, "a")));
-    /// assert_eq!(map.remove(a), None);
+    /// for i in 0..6 {
+    ///     map.insert(i*2);
+    /// }
+    /// map.retain_ref(|_, v| *v > 4);
+    /// assert_eq!(map.len(), 3);
     /// ```
-    pub fn remove_entry(&mut self, index: usize) -> Option<(usize, T)> {
-        Some((index, self.remove(index)?))
+    pub fn retain_ref<P>(&mut self, mut predicate: P) -> usize
+    where
+        P: FnMut(usize, &T) -> bool,
+    {
+        self.retain(|key, val| predicate(key, val))
     }
 
-    /// Returns a reference to the value corresponding to the key.
+    /// Retains only the elements specified by the predicate, threading `state` through every call
+    /// of the predicate, in ascending order of keys.
+    ///
+    /// This makes it possible to decide whether to keep an entry based on the entries seen before
+    /// it. Otherwise this is identical to [`retain_ref`](IndexMap::retain_ref).
     ///
     /// # Examples
     /// ```
     /// use index_map::IndexMap;
+    /// use std::collections::HashSet;
     ///
     /// let mut map = IndexMap::new();
-    /// map.insert("a");
-    /// assert_eq!(map.get(0), Some(&"a"));
-    /// assert_eq!(map.get(1), None);
+    /// map.insert(("fruit", "apple"));
+    /// map.insert(("veg", "carrot"));
+    /// map.insert(("fruit", "pear"));
+    ///
+    /// // Keep only the first entry of each category
+    /// let mut seen = HashSet::new();
+    /// map.retain_with(&mut seen, |seen, _, (category, _)| seen.insert(*category));
+    /// assert_eq!(map.len(), 2);
+    /// assert!(!map.contains_key(2));
     /// ```
-    pub fn get(&self, index: usize) -> Option<&T> {
-        self.data.get(index)?.as_ref().into_inner()
+    pub fn retain_with<S, F>(&mut self, state: &mut S, mut f: F) -> usize
+    where
+        F: FnMut(&mut S, usize, &T) -> bool,
+    {
+        self.retain(|key, val| f(state, key, val))
     }
 
-    /// Returns the key-value pair corresponding to the key.
+    /// Retains only the elements specified by the predicate, like [`retain`](IndexMap::retain),
+    /// but only looks at up to `budget` slots starting from the key `resume_from`.
+    ///
+    /// Returns the key to resume from on the next call, which is the storage length once every
+    /// slot has been visited. Free slots count towards the budget too, so each call does a
+    /// bounded amount of work, however fragmented the map is. A `budget` of zero makes no
+    /// progress.
+    ///
+    /// Entries inserted between calls may or may not be visited, depending on the key they get.
     ///
     /// # Examples
     /// ```
     /// use index_map::IndexMap;
     ///
     /// let mut map = IndexMap::new();
-    /// map.insert("a");
-    /// assert_eq!(map.get_key_value(0), Some((0, &"a")));
-    /// assert_eq!(map.get_key_value(1), None);
+    /// for i in 0..10 {
+    ///     map.insert(i);
+    /// }
+    ///
+    /// let mut next = 0;
+    /// let mut passes = 0;
+    /// while next < 10 {
+    ///     next = map.retain_incremental(next, 4, |_, v| *v % 2 == 0);
+    ///     passes += 1;
+    /// }
+    /// assert_eq!(passes, 3);
+    /// assert_eq!(map.keys().collect::<Vec<_>>(), [0, 2, 4, 6, 8]);
     /// ```
-    pub fn get_key_value(&self, index: usize) -> Option<(usize, &T)> {
-        Some((index, self.get(index)?))
+    pub fn retain_incremental<P>(&mut self, resume_from: usize, budget: usize, mut pred: P) -> usize
+    where
+        P: FnMut(usize, &mut T) -> bool,
+    {
+        let end = resume_from.saturating_add(budget).min(self.data.len());
+
+        for i in resume_from.min(end)..end {
+            if let OptionIndex::Some(val) = &mut self.data[i] {
+                if !pred(i, val) {
+                    // Only drop the value once the map is consistent again, in case it panics.
+                    let val = self.data[i].take();
+                    self.link_free(i);
+                    self.len -= 1;
+                    drop(val);
+                }
+            }
+        }
+
+        end
     }
 
-    /// Returns a mutable reference to the value corresponding to the key.
+    /// Retains only the elements specified by the predicate, and packs the remaining entries into
+    /// the keys `0..len`, keeping them in the same relative order. The allocated memory is kept.
+    ///
+    /// Returns the `(old, new)` keys of every entry that was moved. Entries that aren't listed
+    /// kept their key.
     ///
     /// # Examples
     /// ```
     /// use index_map::IndexMap;
     ///
     /// let mut map = IndexMap::new();
-    /// let a = map.insert("a");
-    /// if let Some(x) = map.get_mut(a) {
-    ///     *x = "b";
+    /// for i in 0..6 {
+    ///     map.insert(i);
     /// }
-    /// assert_eq!(map[a], "b");
+    /// map.remove(1);
+    ///
+    /// let moved = map.retain_compacting(|_, v| *v != 3);
+    /// assert_eq!(moved, [(2, 1), (4, 2), (5, 3)]);
+    /// assert_eq!(map.values().copied().collect::<Vec<_>>(), [0, 2, 4, 5]);
+    /// assert!(map.is_dense());
     /// ```
-    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
-        self.data.get_mut(index)?.as_mut().into_inner()
+    pub fn retain_compacting<P>(&mut self, mut predicate: P) -> Vec<(usize, usize)>
+    where
+        P: FnMut(usize, &mut T) -> bool,
+    {
+        // Rebuilding the free list afterwards, or if the predicate panics, is simpler than keeping
+        // it valid while entries are moved around.
+        let guard = RebuildOnDrop(self);
+        let map = &mut *guard.0;
+        let mut moved = Vec::new();
+        let mut next = 0;
+
+        for i in 0..map.data.len() {
+            let keep = match &mut map.data[i] {
+                OptionIndex::Some(val) => predicate(i, val),
+                _ => continue,
+            };
+
+            if !keep {
+                drop(map.data[i].take());
+                continue;
+            }
+
+            if i != next {
+                map.data.swap(next, i);
+                if let Some(order) = &mut map.order {
+                    order.swap(next, i);
+                }
+                moved.push((i, next));
+            }
+            next += 1;
+        }
+
+        map.data.truncate(next);
+        if let Some(order) = &mut map.order {
+            order.truncate(next);
+        }
+        moved
     }
 
-    /// Retains only the elements specified by the predicate.
+    /// Passes every value by value to the closure, in ascending order of keys. The value the
+    /// closure returns is put back at the same key, and if it returns `None` the entry is removed.
     ///
-    /// In other words, remove all pairs `(k, v)` such that `f(k, &mut v)` returns `false`.
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(String::from("a"));
+    /// map.insert(String::from("b"));
+    /// map.insert(String::from("c"));
+    ///
+    /// map.transform(|key, mut val| {
+    ///     if key == 1 {
+    ///         return None;
+    ///     }
+    ///     val.push('!');
+    ///     Some(val)
+    /// });
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(map[0], "a!");
+    /// assert_eq!(map[2], "c!");
+    /// ```
+    pub fn transform<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, T) -> Option<T>,
+    {
+        for i in 0..self.data.len() {
+            if !self.data[i].is_inner() {
+                continue;
+            }
+
+            // Free the slot before calling `f`, so that the map stays consistent if it panics.
+            let val = self.data[i].take().into_inner();
+            self.link_free(i);
+            self.len -= 1;
+
+            if let Some(new) = val.and_then(|val| f(i, val)) {
+                // The slot was just linked in, so it is still at the head of the free list.
+                self.unlink_free(i);
+                self.data[i] = OptionIndex::Some(new);
+                self.len += 1;
+            }
+        }
+    }
+
+    /// Compacts the map, moving the entries with the largest keys into the free slots with the
+    /// smallest keys, until every key below [`len`](IndexMap::len) is occupied. The allocated
+    /// memory is kept.
+    ///
+    /// Every time an entry is moved, the closure is called with its old key, its new key and a
+    /// mutable reference to the value, so that any references to the entry held elsewhere can be
+    /// fixed up as the compaction goes. Entries are moved from the largest old key downwards.
     ///
     /// # Examples
     /// ```
@@ -459,56 +2122,543 @@ impl<T> IndexMap<T> {
     ///
     /// let mut map = IndexMap::new();
     /// for i in 0..6 {
-    ///     map.insert(i*2);
+    ///     map.insert(i);
     /// }
-    /// map.retain(|k, _| k % 2 == 0);
-    /// assert_eq!(map.len(), 3);
+    /// map.remove(0);
+    /// map.remove(2);
+    ///
+    /// let mut moves = Vec::new();
+    /// map.compact_with(|old, new, val| moves.push((old, new, *val)));
+    /// assert_eq!(moves, [(5, 0, 5), (4, 2, 4)]);
+    /// assert!(map.is_dense());
+    /// assert_eq!(map[0], 5);
     /// ```
-    pub fn retain<P>(&mut self, mut predicate: P)
+    pub fn compact_with<F>(&mut self, mut f: F)
     where
-        P: FnMut(usize, &mut T) -> bool,
+        F: FnMut(usize, usize, &mut T),
     {
-        // Cannot use `self.iter_mut` as we need the pointer to the `OptionIndex` and not the value
-        // contained in it.
-        for (i, v) in self.data.iter_mut().enumerate() {
-            if let OptionIndex::Some(val) = v {
-                if !predicate(i, val) {
-                    *v = if let Some(head) = self.head {
-                        OptionIndex::Index(head)
-                    } else {
-                        OptionIndex::NoIndex
-                    };
+        // The free list is rebuilt once the compaction is done, or if `f` panics partway through,
+        // so it doesn't need to be maintained while moving entries around.
+        let guard = RebuildOnDrop(self);
+        let map = &mut *guard.0;
+        let mut lo = 0;
+
+        loop {
+            let hi = match map.data.iter().rposition(OptionIndex::is_inner) {
+                Some(hi) => hi,
+                None => {
+                    map.data.clear();
+                    break;
+                }
+            };
+            map.data.truncate(hi + 1);
+
+            lo = match map.data[lo..hi].iter().position(|slot| !slot.is_inner()) {
+                Some(i) => lo + i,
+                None => break,
+            };
+
+            map.data.swap(lo, hi);
+            map.data.truncate(hi);
+            if let Some(order) = &mut map.order {
+                order.swap(lo, hi);
+                order.truncate(hi);
+            }
 
-                    self.len -= 1;
-                    self.head = Some(i)
+            if let OptionIndex::Some(val) = &mut map.data[lo] {
+                f(hi, lo, val);
+            }
+            lo += 1;
+        }
+
+        if let Some(order) = &mut map.order {
+            order.truncate(map.data.len());
+        }
+    }
+
+    /// Consumes the map, splitting it into two maps: one with all the entries for which the
+    /// predicate returned `true`, and one with the rest. Every entry keeps its key.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// for i in 0..5 {
+    ///     map.insert(i * 10);
+    /// }
+    ///
+    /// let (even, odd) = map.partition(|k, _| k % 2 == 0);
+    /// assert_eq!(even.len(), 3);
+    /// assert_eq!(odd.len(), 2);
+    /// assert_eq!(even[4], 40);
+    /// assert_eq!(odd[3], 30);
+    /// ```
+    pub fn partition<P>(self, mut predicate: P) -> (Self, Self)
+    where
+        P: FnMut(usize, &T) -> bool,
+    {
+        let IndexMap {
+            data,
+            order,
+            next_seq,
+            capacity_floor,
+            ..
+        } = self;
+
+        let empty = || IndexMap {
+            data: Vec::with_capacity(data.len().max(capacity_floor)),
+            order: order.clone(),
+            next_seq,
+            capacity_floor,
+            ..IndexMap::new()
+        };
+        let mut matching = empty();
+        let mut rest = empty();
+
+        for (i, slot) in data.into_iter().enumerate() {
+            let (a, b) = match slot {
+                OptionIndex::Some(value) if predicate(i, &value) => {
+                    (OptionIndex::Some(value), OptionIndex::NoIndex)
                 }
+                OptionIndex::Some(value) => (OptionIndex::NoIndex, OptionIndex::Some(value)),
+                _ => (OptionIndex::NoIndex, OptionIndex::NoIndex),
+            };
+            matching.data.push(a);
+            rest.data.push(b);
+        }
+
+        matching.rebuild_free_list();
+        rest.rebuild_free_list();
+        (matching, rest)
+    }
+
+    /// Rebuilds the free list and the length purely from which slots contain values.
+    ///
+    /// The map keeps these consistent on its own, so this is never required for correctness. It
+    /// is a normalization routine: after it runs, all the free slots are linked in ascending
+    /// order, so subsequent inserts hand out the lowest free keys first.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// for i in 0..4 {
+    ///     map.insert(i);
+    /// }
+    /// map.remove(0);
+    /// map.remove(2);
+    ///
+    /// map.rebuild_free_list();
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(map.insert(10), 0);
+    /// assert_eq!(map.insert(12), 2);
+    /// ```
+    pub fn rebuild_free_list(&mut self) {
+        self.head = None;
+        self.len = 0;
+
+        // Walk backwards so the lowest free slot ends up at the head of the list.
+        for i in (0..self.data.len()).rev() {
+            if self.data[i].is_inner() {
+                self.len += 1;
+            } else {
+                self.link_free(i);
+            }
+        }
+    }
+
+    /// Truncates the free slots past the largest occupied key, unlinking them from the free list.
+    /// Does not touch the allocation.
+    fn trim_free_tail(&mut self) {
+        // Every slot is free, so all of them can go. This is checked first, so that the map is
+        // fully reset even if `head` was left pointing past the end of an emptied `data`.
+        if self.is_empty() {
+            self.head = None;
+            self.data.clear();
+            return;
+        }
+
+        // This relies on the fact that `||` short-circuits. The map isn't empty, so `data` can't
+        // be either, and `data.last()` *cannot* be None.
+        if self.head.is_none() || self.data.last().unwrap().is_inner() {
+            return;
+        }
+
+        // random default value, the previous check makes sure there are elements, so the if
+        // condition has to be triggered.
+        let mut last = usize::MAX;
+
+        for (i, v) in self.data.iter().enumerate().rev() {
+            if v.is_inner() {
+                last = i;
+                break;
+            }
+        }
+
+        assert_ne!(last, usize::MAX);
+
+        // Every free index more than last is going to be truncated, so they need to be unlinked
+        // from the free list, and head needs to point to an index which is not truncated.
+        //                   ,-- head [ 4 ]   |   Key:
+        // .---.---.---.---.---.              |   *     = element
+        // | * | - | * | * | 1 |              |   -     = No Index
+        // '---'---'---'---'---'              |   <int> = Index
+        //               ^-- last [ 3 ]       |
+        // Take the above data. After shrinking, it would be erroneous for head to still point
+        // to 4, since it will be deleted. Similarly, a kept index could point to a chain of
+        // several truncated indices, all of which need to be skipped.
+        let mut head = None;
+        let mut prev_kept: Option<usize> = None;
+        let mut curr = self.head;
+
+        while let Some(i) = curr {
+            curr = self.data[i].as_ref().into_index();
+
+            // `last` contains an element, so it can't be in the free list.
+            if i < last {
+                match prev_kept {
+                    Some(prev) => self.data[prev] = OptionIndex::Index(i),
+                    None => head = Some(i),
+                }
+                prev_kept = Some(i);
+            }
+        }
+
+        if let Some(prev) = prev_kept {
+            self.data[prev] = OptionIndex::NoIndex;
+        }
+        self.head = head;
+
+        // Truncate expects length, not the index of last element
+        self.data.truncate(last + 1);
+    }
+
+    /// Converts `range` into a range of slot indices, clamped to the length of `data`.
+    fn key_range<R: RangeBounds<usize>>(&self, range: R) -> Range<usize> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => usize::MAX,
+        };
+
+        let end = end.min(self.data.len());
+        start.min(end)..end
+    }
+
+    /// Records that a value was just inserted at `index`, if insertion order is being tracked.
+    fn record_insertion(&mut self, index: usize) {
+        if let Some(order) = &mut self.order {
+            if order.len() <= index {
+                order.resize(index + 1, 0);
+            }
+            order[index] = self.next_seq;
+            self.next_seq += 1;
+        }
+    }
+
+    /// Drops the insertion order entries for slots which no longer exist.
+    fn shrink_order(&mut self) {
+        if let Some(order) = &mut self.order {
+            order.truncate(self.data.len());
+            order.shrink_to_fit();
+        }
+    }
+
+    /// Pushes the slot at `index` onto the front of the free list. The slot must not contain a
+    /// value.
+    fn link_free(&mut self, index: usize) {
+        self.data[index] = match self.head {
+            Some(head) => OptionIndex::Index(head),
+            None => OptionIndex::NoIndex,
+        };
+        self.head = Some(index);
+    }
+
+    /// Removes the free slot at `index` from the free list, leaving it as `NoIndex`.
+    ///
+    /// Since the free list is singly linked, this has to walk the list to find the previous slot,
+    /// unless `index` happens to be the head.
+    fn unlink_free(&mut self, index: usize) {
+        let next = self.data[index].take();
+        debug_assert!(!next.is_inner(), "unlinking an occupied slot");
+
+        if self.head == Some(index) {
+            self.head = next.into_index();
+            return;
+        }
+
+        let mut prev = self.head.expect("free slot missing from the free list");
+        loop {
+            match self.data[prev] {
+                OptionIndex::Index(i) if i == index => break,
+                OptionIndex::Index(i) => prev = i,
+                _ => unreachable!("free slot missing from the free list"),
             }
         }
+
+        self.data[prev] = next;
+    }
+
+    /// Extends `data` up to `len` slots, linking every new slot into the free list.
+    fn grow_to(&mut self, len: usize) {
+        if len <= self.data.len() {
+            return;
+        }
+
+        self.data.reserve(len - self.data.len());
+        while self.data.len() < len {
+            self.data.push(OptionIndex::NoIndex);
+            self.link_free(self.data.len() - 1);
+        }
+    }
+
+    /// Inserts a value at a key which is not occupied, either by taking the slot out of the free
+    /// list, or by growing `data` and padding it with free slots up to `key`.
+    fn insert_vacant(&mut self, key: usize, value: T) -> &mut T {
+        debug_assert!(!self.contains_key(key), "inserting into an occupied slot");
+
+        if key < self.data.len() {
+            self.unlink_free(key);
+            self.data[key] = OptionIndex::Some(value);
+        } else {
+            // Make sure the padding and the value itself only cause a single reallocation.
+            self.data.reserve(key - self.data.len() + 1);
+            self.grow_to(key);
+            self.data.push(OptionIndex::Some(value));
+        }
+
+        self.len += 1;
+        self.record_insertion(key);
+
+        match &mut self.data[key] {
+            OptionIndex::Some(value) => value,
+            _ => unreachable!("value was just inserted"),
+        }
+    }
+}
+
+/// Rebuilds the free list of the map when dropped, for operations which move entries around
+/// without keeping the free list valid in the meantime. Since it also runs during unwinding, the
+/// map stays consistent if a user-provided closure panics partway through.
+struct RebuildOnDrop<'a, T>(&'a mut IndexMap<T>);
+
+impl<T> Drop for RebuildOnDrop<'_, T> {
+    fn drop(&mut self) {
+        self.0.rebuild_free_list();
+    }
+}
+
+/// Clears the map when dropped, so that it is left empty, rather than holding values which the
+/// bookkeeping doesn't know about, if a destructor panics partway through clearing it.
+struct ClearOnDrop<'a, T>(&'a mut IndexMap<T>);
+
+impl<T> Drop for ClearOnDrop<'_, T> {
+    fn drop(&mut self) {
+        self.0.clear();
+    }
+}
+
+impl<T: Clone> Clone for IndexMap<T> {
+    fn clone(&self) -> Self {
+        let mut data = self.data.clone();
+        // A clone starts out with a fresh allocation, which should still respect the floor.
+        data.reserve_exact(self.capacity_floor.saturating_sub(data.len()));
+
+        Self {
+            data,
+            head: self.head,
+            len: self.len,
+            order: self.order.clone(),
+            next_seq: self.next_seq,
+            capacity_floor: self.capacity_floor,
+        }
+    }
+}
+
+impl<T> Default for IndexMap<T> {
+    /// Creates an empty `IndexMap`, same as calling new.
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Collects values into a new map, where they get the keys `0, 1, 2, ...` in order.
+///
+/// # Examples
+/// ```
+/// use index_map::IndexMap;
+///
+/// let map: IndexMap<_> = vec!["a", "b", "c"].into_iter().collect();
+/// assert_eq!(map.len(), 3);
+/// assert_eq!(map[2], "c");
+/// ```
+impl<T> FromIterator<T> for IndexMap<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+/// Inserts every value, with keys generated exactly as if each was passed to
+/// [`insert`](IndexMap::insert), so free slots get reused first.
+///
+/// # Examples
+/// ```
+/// use index_map::IndexMap;
+///
+/// let mut map = IndexMap::new();
+/// map.insert("a");
+/// map.insert("b");
+/// map.remove(0);
+///
+/// map.extend(vec!["c", "d"]);
+/// assert_eq!(map, &[(0, "c"), (1, "b"), (2, "d")][..]);
+/// ```
+impl<T> Extend<T> for IndexMap<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        // Only the values which don't fit in the free slots need new space.
+        let free = self.data.len() - self.len;
+        self.reserve(iter.size_hint().0.saturating_sub(free));
+
+        for value in iter {
+            self.insert(value);
+        }
     }
 }
 
-impl<T: Clone> Clone for IndexMap<T> {
-    fn clone(&self) -> Self {
-        Self {
-            data: self.data.clone(),
-            head: self.head,
-            len: self.len,
+/// Two maps are equal if they contain the same keys with equal values. Which of the other keys
+/// are free slots, and the order they will be reused in, doesn't matter.
+///
+/// # Examples
+/// ```
+/// use index_map::IndexMap;
+///
+/// let mut a = IndexMap::new();
+/// a.insert("a");
+/// a.insert("b");
+/// a.remove(0);
+///
+/// let mut b = IndexMap::new();
+/// b.insert("x");
+/// b.insert("b");
+/// b.insert("c");
+/// b.remove(2);
+/// b.remove(0);
+///
+/// assert_eq!(a, b);
+/// ```
+impl<T: PartialEq> PartialEq for IndexMap<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for IndexMap<T> {}
+
+/// Maps are compared lexicographically by their `(key, value)` pairs in ascending order of keys,
+/// consistently with their equality.
+impl<T: PartialOrd> PartialOrd for IndexMap<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord> Ord for IndexMap<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+/// Hashes the `(key, value)` pairs in ascending order of keys, so that maps which are equal hash
+/// the same regardless of their free slots.
+impl<T: Hash> Hash for IndexMap<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len);
+        for entry in self.iter() {
+            entry.hash(state);
         }
     }
 }
 
-impl<T> Default for IndexMap<T> {
-    /// Creates an empty `IndexMap`, same as calling new.
-    fn default() -> Self {
-        Self::new()
+/// Compares the entries of the map, in ascending order of keys, with a slice of `(key, value)`
+/// pairs.
+///
+/// # Examples
+/// ```
+/// use index_map::IndexMap;
+///
+/// let mut map = IndexMap::new();
+/// map.insert("a");
+/// map.insert("b");
+/// map.insert("c");
+/// map.remove(1);
+///
+/// assert_eq!(map, &[(0, "a"), (2, "c")][..]);
+/// assert_ne!(map, &[(2, "c"), (0, "a")][..]);
+/// ```
+impl<T: PartialEq> PartialEq<[(usize, T)]> for IndexMap<T> {
+    fn eq(&self, other: &[(usize, T)]) -> bool {
+        self.len == other.len()
+            && self
+                .iter()
+                .zip(other)
+                .all(|((key, val), (other_key, other_val))| key == *other_key && val == other_val)
+    }
+}
+
+impl<T: PartialEq> PartialEq<&[(usize, T)]> for IndexMap<T> {
+    fn eq(&self, other: &&[(usize, T)]) -> bool {
+        *self == **other
     }
 }
 
 use core::fmt;
 
+/// With the alternate flag (`{:#?}`), a `__free_list` pseudo-entry is printed after the entries,
+/// listing the free slots in the order they will be handed out, to help debug fragmentation.
 impl<T: fmt::Debug> fmt::Debug for IndexMap<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_map().entries(self.iter()).finish()
+        let alternate = f.alternate();
+        let mut map = f.debug_map();
+        map.entries(self.iter());
+        if alternate {
+            map.entry(&format_args!("__free_list"), &FreeList(self));
+        }
+        map.finish()
+    }
+}
+
+struct FreeList<'a, T>(&'a IndexMap<T>);
+
+impl<T> fmt::Debug for FreeList<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let map = self.0;
+        let chain = core::iter::successors(map.head, |&i| map.data[i].as_ref().into_index());
+
+        f.debug_struct("FreeList")
+            .field("head", &map.head)
+            .field("chain", &DebugIter(chain))
+            .finish()
+    }
+}
+
+struct DebugIter<I>(I);
+
+impl<I: Iterator + Clone> fmt::Debug for DebugIter<I>
+where
+    I::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.clone()).finish()
     }
 }
 
@@ -539,6 +2689,7 @@ impl<T> IndexMut<usize> for IndexMap<T> {
 #[cfg(test)]
 mod tests {
     use super::{IndexMap, OptionIndex as OI};
+    use alloc::vec::Vec;
 
     fn assert_state<T: Eq + core::fmt::Debug>(
         map: &IndexMap<T>,
@@ -686,5 +2837,425 @@ mod tests {
         map.shrink_to_fit();
 
         assert_state(&map, &[OI::Some('a'), OI::NoIndex, OI::Some('c')], Some(1));
+
+        let mut map = IndexMap::new();
+
+        let _ = map.insert('a');
+        let b = map.insert('b');
+        let _ = map.insert('c');
+        let d = map.insert('d');
+        let e = map.insert('e');
+
+        map.remove(e);
+        map.remove(d);
+        map.remove(b);
+
+        assert_state(
+            &map,
+            &[
+                OI::Some('a'),
+                OI::Index(3),
+                OI::Some('c'),
+                OI::Index(4),
+                OI::NoIndex,
+            ],
+            Some(1),
+        );
+
+        map.shrink_to_fit();
+
+        assert_state(&map, &[OI::Some('a'), OI::NoIndex, OI::Some('c')], Some(1));
+    }
+
+    #[test]
+    fn test_shrink_with_slack() {
+        let mut map = IndexMap::with_capacity(64);
+
+        let a = map.insert('a');
+        let b = map.insert('b');
+        map.insert('c');
+        let d = map.insert('d');
+        map.remove(b);
+        map.remove(d);
+
+        map.shrink_with_slack(3);
+        assert_state(&map, &[OI::Some('a'), OI::NoIndex, OI::Some('c')], Some(1));
+        assert_eq!(map.capacity(), 6);
+
+        // Growing back up to the slack doesn't reallocate.
+        for _ in 0..4 {
+            map.insert('e');
+        }
+        assert_eq!(map.capacity(), 6);
+
+        map.shrink_with_slack(0);
+        assert_eq!(map.capacity(), 6);
+        map.shrink_with_slack(10);
+        assert_eq!(map.capacity(), 16);
+
+        map.clear();
+        map.insert('a');
+        map.remove(a);
+        map.shrink_with_slack(2);
+        assert_state(&map, &[], None);
+        assert_eq!(map.capacity(), 2);
+    }
+
+    #[test]
+    fn test_max_keys() {
+        use alloc::format;
+
+        // `MAX_KEYS` is exactly as many slots as a `Vec` allows before its capacity overflows,
+        // rather than the allocator merely failing to provide them.
+        let mut data: Vec<OI<u64>> = Vec::new();
+        let mut overflows = |slots| {
+            let err = data.try_reserve(slots).unwrap_err();
+            format!("{:?}", err).contains("CapacityOverflow")
+        };
+        assert!(!overflows(IndexMap::<u64>::MAX_KEYS));
+        assert!(overflows(IndexMap::<u64>::MAX_KEYS + 1));
+
+        // So a key of `MAX_KEYS` can never be inserted, and the map is left alone.
+        let mut map = IndexMap::new();
+        map.insert(0u64);
+        let key = IndexMap::<u64>::MAX_KEYS;
+        assert_eq!(map.try_insert_at(key, 1), Err((key, 1)));
+        assert_state(&map, &[OI::Some(0)], None);
+        assert_eq!(map.insert(2), 1);
+    }
+
+    #[test]
+    fn test_trim_tail() {
+        let mut map = IndexMap::with_capacity(8);
+        for c in "abcdef".chars() {
+            map.insert(c);
+        }
+
+        map.remove(4);
+        map.remove(1);
+        map.remove(5);
+        map.trim_tail();
+        assert_state(
+            &map,
+            &[OI::Some('a'), OI::NoIndex, OI::Some('c'), OI::Some('d')],
+            Some(1),
+        );
+        assert!(map.capacity() >= 8);
+
+        // Nothing to trim.
+        map.trim_tail();
+        assert_eq!(map.data.len(), 4);
+
+        map.clear();
+        map.trim_tail();
+        assert_state(&map, &[], None);
+    }
+
+    #[test]
+    fn test_remove_trim() {
+        let mut map = IndexMap::new();
+
+        let a = map.insert('a');
+        let b = map.insert('b');
+        let c = map.insert('c');
+        let d = map.insert('d');
+        let e = map.insert('e');
+
+        map.remove(b);
+        map.remove(d);
+        assert_eq!(map.remove_trim(a), Some('a'));
+        assert_state(
+            &map,
+            &[
+                OI::Index(3),
+                OI::NoIndex,
+                OI::Some('c'),
+                OI::Index(1),
+                OI::Some('e'),
+            ],
+            Some(0),
+        );
+
+        assert_eq!(map.remove_trim(e), Some('e'));
+        assert_state(&map, &[OI::Index(1), OI::NoIndex, OI::Some('c')], Some(0));
+
+        // Removing a free last slot doesn't touch anything.
+        assert_eq!(map.remove_trim(d), None);
+        assert_eq!(map.remove_trim(usize::MAX), None);
+
+        assert_eq!(map.remove_trim(c), Some('c'));
+        assert_state(&map, &[], None);
+        assert!(map.is_empty());
+
+        let f = map.insert('f');
+        assert_eq!(map.remove_trim(f), Some('f'));
+        assert_state(&map, &[], None);
+
+        // The free slots already at the end are trimmed along with the largest key.
+        let mut map = IndexMap::with_insertion_order_tracking();
+        for i in 0..3 {
+            map.insert(i);
+        }
+        map.remove(2);
+        assert_eq!(map.remove_trim(1), Some(1));
+        assert_state(&map, &[OI::Some(0)], None);
+        assert_eq!(map.order.as_ref().unwrap().len(), 1);
+
+        assert_eq!(map.try_insert_at(3, 3), Ok(None));
+        let values: Vec<_> = map.iter_insertion_order().map(|(_, v)| *v).collect();
+        assert_eq!(values, [0, 3]);
+    }
+
+    #[test]
+    fn test_reserve_for_keys() {
+        let mut map = IndexMap::new();
+        map.insert('a');
+        map.reserve_for_keys(3);
+        assert_state(
+            &map,
+            &[OI::Some('a'), OI::NoIndex, OI::Index(1), OI::Index(2)],
+            Some(3),
+        );
+
+        // Keys which already have slots don't add any.
+        map.reserve_for_keys(2);
+        assert_eq!(map.data.len(), 4);
+
+        let capacity = map.capacity();
+        map.insert_at_if_free(2, 'b').unwrap();
+        assert_state(
+            &map,
+            &[OI::Some('a'), OI::NoIndex, OI::Some('b'), OI::Index(1)],
+            Some(3),
+        );
+        assert_eq!(map.insert('c'), 3);
+        assert_eq!(map.insert('d'), 1);
+        assert_eq!(map.insert('e'), 4);
+        assert!(map.capacity() >= capacity);
+    }
+
+    #[test]
+    fn test_insert_at() {
+        let mut map = IndexMap::new();
+
+        // Past the end: the padding is linked into the free list.
+        assert_eq!(map.insert_at(3, 'd'), None);
+        assert_state(
+            &map,
+            &[OI::NoIndex, OI::Index(0), OI::Index(1), OI::Some('d')],
+            Some(2),
+        );
+
+        // A free slot in the middle of the free list is spliced out.
+        assert_eq!(map.insert_at(1, 'b'), None);
+        assert_state(
+            &map,
+            &[OI::NoIndex, OI::Some('b'), OI::Index(0), OI::Some('d')],
+            Some(2),
+        );
+
+        // The head of the free list.
+        assert_eq!(map.insert_at(2, 'c'), None);
+        assert_state(
+            &map,
+            &[OI::NoIndex, OI::Some('b'), OI::Some('c'), OI::Some('d')],
+            Some(0),
+        );
+
+        // An occupied key is overwritten, leaving the free list alone.
+        assert_eq!(map.insert_at(1, 'B'), Some('b'));
+        assert_state(
+            &map,
+            &[OI::NoIndex, OI::Some('B'), OI::Some('c'), OI::Some('d')],
+            Some(0),
+        );
+        assert_eq!(map.len(), 3);
+
+        assert_eq!(map.insert('a'), 0);
+        assert_eq!(map.insert('e'), 4);
+    }
+
+    #[test]
+    fn test_clear_resets_head() {
+        let mut map = IndexMap::new();
+        map.insert('a');
+        map.insert('b');
+        map.remove(0);
+        assert_state(&map, &[OI::NoIndex, OI::Some('b')], Some(0));
+
+        map.clear();
+        assert_state(&map, &[], None);
+
+        assert_eq!(map.insert('c'), 0);
+        assert_state(&map, &[OI::Some('c')], None);
+    }
+
+    #[test]
+    fn test_shrink_to_fit_after_clear() {
+        let mut map = IndexMap::new();
+        for i in 0..1000 {
+            map.insert(i);
+        }
+        for i in (0..1000).step_by(7) {
+            map.remove(i);
+        }
+
+        map.clear();
+        map.shrink_to_fit();
+        assert_state(&map, &[], None);
+        assert_eq!(map.capacity(), 0);
+        assert_eq!(map.insert(0), 0);
+    }
+
+    /// The exact scenario illustrated in the crate documentation.
+    #[test]
+    fn test_crate_doc_diagram() {
+        let mut map = IndexMap::new();
+        for c in ['a', 'b', 'c'] {
+            map.insert(c);
+        }
+        assert_state(&map, &[OI::Some('a'), OI::Some('b'), OI::Some('c')], None);
+
+        map.remove(1);
+        assert_state(&map, &[OI::Some('a'), OI::NoIndex, OI::Some('c')], Some(1));
+
+        map.remove(2);
+        assert_state(&map, &[OI::Some('a'), OI::NoIndex, OI::Index(1)], Some(2));
+
+        assert_eq!(map.insert('d'), 2);
+        assert_state(&map, &[OI::Some('a'), OI::NoIndex, OI::Some('d')], Some(1));
+
+        // Nothing past the last element is free, so there is nothing to trim.
+        map.shrink_to_fit();
+        assert_state(&map, &[OI::Some('a'), OI::NoIndex, OI::Some('d')], Some(1));
+    }
+
+    #[test]
+    fn test_insert_vacant() {
+        let mut map = IndexMap::new();
+
+        for i in 0..4 {
+            map.insert(i);
+        }
+        map.remove(0);
+        map.remove(2);
+        map.remove(1);
+        assert_state(
+            &map,
+            &[OI::NoIndex, OI::Index(2), OI::Index(0), OI::Some(3)],
+            Some(1),
+        );
+
+        // Unlinking from the middle of the list
+        map.insert_vacant(2, 2);
+        assert_state(
+            &map,
+            &[OI::NoIndex, OI::Index(0), OI::Some(2), OI::Some(3)],
+            Some(1),
+        );
+
+        // Unlinking the head
+        map.insert_vacant(1, 1);
+        assert_state(
+            &map,
+            &[OI::NoIndex, OI::Some(1), OI::Some(2), OI::Some(3)],
+            Some(0),
+        );
+
+        // Padding past the end
+        map.insert_vacant(6, 6);
+        assert_state(
+            &map,
+            &[
+                OI::NoIndex,
+                OI::Some(1),
+                OI::Some(2),
+                OI::Some(3),
+                OI::Index(0),
+                OI::Index(4),
+                OI::Some(6),
+            ],
+            Some(5),
+        );
+        assert_eq!(map.len(), 4);
+    }
+
+    #[test]
+    fn test_rebuild_free_list() {
+        let mut map = IndexMap::new();
+
+        for i in 0..5 {
+            map.insert(i);
+        }
+        map.remove(3);
+        map.remove(1);
+
+        // Deliberately corrupt the bookkeeping.
+        map.head = Some(4);
+        map.len = 0;
+
+        map.rebuild_free_list();
+        assert_state(
+            &map,
+            &[
+                OI::Some(0),
+                OI::Index(3),
+                OI::Some(2),
+                OI::NoIndex,
+                OI::Some(4),
+            ],
+            Some(1),
+        );
+        assert_eq!(map.len(), 3);
+
+        assert_eq!(map.insert(1), 1);
+        assert_eq!(map.insert(3), 3);
+        assert_eq!(map.insert(5), 5);
+        assert_eq!(map.remove(2), Some(2));
+        assert_eq!(map.insert(2), 2);
+        assert_eq!(map.len(), 6);
+        for i in 0..6 {
+            assert_eq!(map[i], i);
+        }
+    }
+
+    #[test]
+    fn test_pop_front_swap_order() {
+        let mut map = IndexMap::with_insertion_order_tracking();
+        for i in 0..3 {
+            map.insert(i);
+        }
+
+        map.pop_front_swap();
+        assert_state(&map, &[OI::Some(2), OI::Some(1)], None);
+        assert_eq!(map.order.as_ref().unwrap().len(), 2);
+
+        map.insert(3);
+        let values: Vec<_> = map.iter_insertion_order().map(|(_, v)| *v).collect();
+        assert_eq!(values, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_compact_with_order() {
+        let mut map = IndexMap::with_insertion_order_tracking();
+        for i in 0..6 {
+            map.insert(i);
+        }
+        map.remove(1);
+        map.remove(4);
+        map.remove(5);
+
+        map.compact_with(|_, _, _| {});
+        assert_state(&map, &[OI::Some(0), OI::Some(3), OI::Some(2)], None);
+        assert_eq!(map.order.as_ref().unwrap().len(), 3);
+
+        map.insert(6);
+        let values: Vec<_> = map.iter_insertion_order().map(|(_, v)| *v).collect();
+        assert_eq!(values, [0, 2, 3, 6]);
+
+        map.retain(|_, _| false);
+        map.compact_with(|_, _, _| {});
+        assert_state(&map, &[], None);
+        assert!(map.order.as_ref().unwrap().is_empty());
     }
 }
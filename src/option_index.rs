@@ -104,7 +104,7 @@ mod tests {
 
     #[test]
     fn test_take() {
-        for i in vec![make_some(0), make_idx(1), make_noidx()] {
+        for i in [make_some(0), make_idx(1), make_noidx()] {
             let mut opt = i;
             assert_eq!(opt.take(), i);
             assert_eq!(opt, make_noidx());
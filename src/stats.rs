@@ -0,0 +1,64 @@
+use super::{IndexMap, OptionIndex};
+
+/// A snapshot of how full and how fragmented an [`IndexMap`] is.
+///
+/// This `struct` is created by the [`stats`](IndexMap::stats) method on [`IndexMap`]. See its
+/// documentation for more.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapStats {
+    /// The number of entries in the map, same as [`len`](IndexMap::len).
+    pub len: usize,
+    /// The number of slots allocated, same as [`capacity`](IndexMap::capacity).
+    pub capacity: usize,
+    /// The number of free slots below the end of the map's storage, which the next inserts will
+    /// reuse before allocating new keys.
+    pub free_slots: usize,
+    /// The largest key in use, or `None` if the map is empty.
+    pub max_occupied: Option<usize>,
+    /// The fraction of slots in the map's storage which are free, from `0.0` for a dense map up to
+    /// `1.0` for a map whose entries were all removed. A map with no slots at all, such as a new
+    /// or cleared one, has a fragmentation of `0.0`.
+    pub fragmentation: f64,
+}
+
+impl<T> IndexMap<T> {
+    /// Returns the size, capacity and fragmentation of the map in one go.
+    ///
+    /// Apart from `max_occupied`, which scans back over any free slots at the end of the map, all
+    /// of these are tracked by the map and O(1) to compute.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::with_capacity(8);
+    /// for i in 0..4 {
+    ///     map.insert(i);
+    /// }
+    /// map.remove(1);
+    /// map.remove(3);
+    ///
+    /// let stats = map.stats();
+    /// assert_eq!(stats.len, 2);
+    /// assert!(stats.capacity >= 8);
+    /// assert_eq!(stats.free_slots, 2);
+    /// assert_eq!(stats.max_occupied, Some(2));
+    /// assert_eq!(stats.fragmentation, 0.5);
+    /// ```
+    pub fn stats(&self) -> MapStats {
+        let free_slots = self.data.len() - self.len;
+        let fragmentation = if self.data.is_empty() {
+            0.0
+        } else {
+            free_slots as f64 / self.data.len() as f64
+        };
+
+        MapStats {
+            len: self.len,
+            capacity: self.capacity(),
+            free_slots,
+            max_occupied: self.data.iter().rposition(OptionIndex::is_inner),
+            fragmentation,
+        }
+    }
+}
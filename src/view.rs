@@ -0,0 +1,146 @@
+use super::{IndexMap, OptionIndex};
+use core::fmt;
+
+/// A shared view of every entry of a map except one, whose value is borrowed mutably elsewhere.
+///
+/// This `struct` is created by the [`split_one_mut`](IndexMap::split_one_mut) method on
+/// [`IndexMap`]. See its documentation for more.
+pub struct RestView<'a, T> {
+    before: &'a [OptionIndex<T>],
+    after: &'a [OptionIndex<T>],
+    key: usize,
+}
+
+impl<T> Clone for RestView<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RestView<'_, T> {}
+
+impl<T: fmt::Debug> fmt::Debug for RestView<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T> RestView<'a, T> {
+    /// Returns the key which is excluded from this view.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert(1);
+    /// let (_, rest) = map.split_one_mut(a).unwrap();
+    /// assert_eq!(rest.key(), a);
+    /// ```
+    pub fn key(&self) -> usize {
+        self.key
+    }
+
+    /// Returns a reference to the value corresponding to the key, or `None` if the key isn't
+    /// present or is the excluded key.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert(1);
+    /// let b = map.insert(2);
+    /// let (_, rest) = map.split_one_mut(a).unwrap();
+    /// assert_eq!(rest.get(b), Some(&2));
+    /// assert_eq!(rest.get(a), None);
+    /// ```
+    pub fn get(&self, key: usize) -> Option<&'a T> {
+        let slot = if key < self.key {
+            self.before.get(key)?
+        } else if key > self.key {
+            self.after.get(key - self.key - 1)?
+        } else {
+            return None;
+        };
+
+        slot.as_ref().into_inner()
+    }
+
+    /// Returns `true` if the view contains a value for the specified key.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert(1);
+    /// let b = map.insert(2);
+    /// let (_, rest) = map.split_one_mut(a).unwrap();
+    /// assert!(rest.contains_key(b));
+    /// assert!(!rest.contains_key(a));
+    /// ```
+    pub fn contains_key(&self, key: usize) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// An iterator visiting all key-value pairs in the view, in ascending order of keys.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// map.insert(1);
+    /// let b = map.insert(2);
+    /// map.insert(3);
+    /// let (_, rest) = map.split_one_mut(b).unwrap();
+    /// assert_eq!(rest.iter().collect::<Vec<_>>(), [(0, &1), (2, &3)]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &'a T)> {
+        let offset = self.key + 1;
+        let before = self.before.iter().enumerate();
+        let after = self
+            .after
+            .iter()
+            .enumerate()
+            .map(move |(i, slot)| (i + offset, slot));
+
+        before
+            .chain(after)
+            .filter_map(|(i, slot)| Some((i, slot.as_ref().into_inner()?)))
+    }
+}
+
+impl<T> IndexMap<T> {
+    /// Returns a mutable reference to the value corresponding to the key, along with a shared
+    /// view of every other entry in the map, or `None` if the key isn't present.
+    ///
+    /// This allows reading the rest of the map while modifying one value, which borrowing the map
+    /// mutably and immutably at the same time would not allow.
+    ///
+    /// # Examples
+    /// ```
+    /// use index_map::IndexMap;
+    ///
+    /// let mut map = IndexMap::new();
+    /// let a = map.insert(1);
+    /// map.insert(2);
+    /// map.insert(3);
+    ///
+    /// let (val, rest) = map.split_one_mut(a).unwrap();
+    /// *val = rest.iter().map(|(_, v)| v).sum();
+    /// assert_eq!(map[a], 5);
+    /// ```
+    pub fn split_one_mut(&mut self, key: usize) -> Option<(&mut T, RestView<'_, T>)> {
+        if !self.contains_key(key) {
+            return None;
+        }
+
+        let (before, rest) = self.data.split_at_mut(key);
+        let (slot, after) = rest.split_first_mut()?;
+        let val = slot.as_mut().into_inner()?;
+
+        Some((val, RestView { before, after, key }))
+    }
+}
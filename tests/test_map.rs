@@ -66,7 +66,7 @@ fn test_clone() {
     assert_eq!(m2.len(), 2);
 }
 
-thread_local! { static DROP_VECTOR: RefCell<Vec<i32>> = RefCell::new(Vec::new()) }
+thread_local! { static DROP_VECTOR: RefCell<Vec<i32>> = const { RefCell::new(Vec::new()) } }
 
 #[derive(Hash, PartialEq, Eq)]
 struct Droppable {
@@ -381,7 +381,7 @@ fn test_values_mut() {
     map.insert(3);
 
     for value in map.values_mut() {
-        *value = (*value) * 2
+        *value *= 2
     }
     let values: Vec<_> = map.values().cloned().collect();
     assert_eq!(values.len(), 3);
@@ -544,7 +544,7 @@ fn test_index_nonexistent() {
     map.insert(1);
     map.insert(4);
 
-    map[4];
+    let _ = map[4];
 }
 
 #[test]
@@ -582,3 +582,2468 @@ fn test_retain() {
     assert_eq!(map[4], 40);
     assert_eq!(map[6], 60);
 }
+
+#[test]
+fn test_drain_sorted_by() {
+    let mut map = IM::new();
+    for i in [5, 1, 4, 1, 3] {
+        map.insert(i);
+    }
+    map.remove(2);
+
+    let drained: Vec<_> = map.drain_sorted_by(|a, b| b.cmp(a)).collect();
+    assert_eq!(drained, [(0, 5), (4, 3), (1, 1), (3, 1)]);
+    assert!(map.is_empty());
+
+    assert_eq!(map.insert(7), 0);
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn test_first_key_value_mut() {
+    let mut map = IM::new();
+    assert_eq!(map.first_key_value_mut(), None);
+
+    for i in 0..5 {
+        map.insert(i);
+    }
+    map.remove(0);
+    map.remove(1);
+    map.remove(3);
+
+    let (key, val) = map.first_key_value_mut().unwrap();
+    assert_eq!(key, 2);
+    *val = 20;
+    assert_eq!(map[2], 20);
+
+    map.remove(2);
+    assert_eq!(map.first_key_value_mut(), Some((4, &mut 4)));
+}
+
+#[test]
+fn test_capacity_bytes() {
+    let m: IM<u64> = IM::new();
+    assert_eq!(m.capacity_bytes(), 0);
+
+    let mut m: IM<u64> = IM::with_capacity(16);
+    let slot = m.capacity_bytes() / m.capacity();
+    assert!(slot >= std::mem::size_of::<u64>());
+
+    for i in 0..100 {
+        m.insert(i);
+    }
+    assert_eq!(m.capacity_bytes(), m.capacity() * slot);
+}
+
+#[test]
+fn test_entry() {
+    use index_map::Entry;
+
+    let mut map = IM::new();
+    let a = map.insert(10);
+    let b = map.insert(20);
+    map.insert(30);
+    map.remove(a);
+    map.remove(b);
+
+    match map.entry(2) {
+        Entry::Occupied(mut o) => {
+            assert_eq!(o.key(), 2);
+            assert_eq!(o.insert(31), 30);
+        }
+        Entry::Vacant(_) => unreachable!(),
+    }
+
+    // `a` is not at the head of the free list, so it has to be unlinked from the middle.
+    match map.entry(a) {
+        Entry::Vacant(v) => {
+            assert!(!v.would_grow());
+            assert_eq!(*v.insert(11), 11);
+        }
+        Entry::Occupied(_) => unreachable!(),
+    }
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.insert(21), b);
+
+    match map.entry(5) {
+        Entry::Vacant(v) => {
+            assert!(v.would_grow());
+            v.insert(51);
+        }
+        Entry::Occupied(_) => unreachable!(),
+    }
+    assert_eq!(map.len(), 4);
+    assert!(!map.contains_key(3));
+    assert!(!map.contains_key(4));
+
+    // The padding is handed out before new slots are pushed.
+    let mut keys = vec![map.insert(0), map.insert(0)];
+    keys.sort_unstable();
+    assert_eq!(keys, [3, 4]);
+    assert_eq!(map.insert(0), 6);
+}
+
+#[test]
+fn test_extend_from_slice() {
+    let mut map = IM::new();
+    for i in 0..4 {
+        map.insert(i);
+    }
+    map.remove(1);
+    map.remove(2);
+    map.shrink_to_fit();
+    let cap = map.capacity();
+
+    map.extend_from_slice(&[10, 20]);
+    assert_eq!(map.capacity(), cap);
+    assert_eq!(map.len(), 4);
+    assert_eq!(map[1], 20);
+    assert_eq!(map[2], 10);
+
+    map.extend_from_slice(&[30, 40, 50]);
+    assert_eq!(map.len(), 7);
+    assert_eq!(
+        map.values().copied().collect::<Vec<_>>(),
+        [0, 20, 10, 3, 30, 40, 50]
+    );
+}
+
+#[test]
+fn test_singleton() {
+    let (key, mut map) = IM::singleton('a');
+    assert_eq!(key, 0);
+    assert_eq!(map.len(), 1);
+    assert_eq!(map.get(key), Some(&'a'));
+
+    assert_eq!(map.insert('b'), 1);
+}
+
+#[test]
+fn test_key_bounds() {
+    let mut map = IM::new();
+    assert_eq!(map.key_bounds(), None);
+
+    for i in 0..6 {
+        map.insert(i);
+    }
+    assert_eq!(map.key_bounds(), Some((0, 5)));
+
+    map.remove(0);
+    map.remove(1);
+    map.remove(5);
+    assert_eq!(map.key_bounds(), Some((2, 4)));
+
+    map.remove(2);
+    map.remove(3);
+    assert_eq!(map.key_bounds(), Some((4, 4)));
+
+    map.remove(4);
+    assert_eq!(map.key_bounds(), None);
+}
+
+#[test]
+fn test_occupied_entry_remove() {
+    use index_map::Entry;
+
+    let mut map = IM::new();
+    for i in 0..4 {
+        map.insert(i * 10);
+    }
+
+    for key in [1, 2] {
+        match map.entry(key) {
+            Entry::Occupied(o) if key == 1 => assert_eq!(o.remove(), 10),
+            Entry::Occupied(o) => assert_eq!(o.remove_entry(), (2, 20)),
+            Entry::Vacant(_) => unreachable!(),
+        }
+    }
+    assert_eq!(map.len(), 2);
+    assert!(matches!(map.entry(1), Entry::Vacant(_)));
+
+    // The freed keys are reused, most recently freed first.
+    assert_eq!(map.insert(5), 2);
+    assert_eq!(map.insert(6), 1);
+    assert_eq!(map.insert(7), 4);
+}
+
+#[test]
+fn test_iter_insertion_order() {
+    let mut map = IM::with_insertion_order_tracking();
+    for i in 0..5 {
+        map.insert(i);
+    }
+    map.remove(1);
+    map.remove(3);
+    map.insert(5);
+    map.insert(6);
+    map.entry(7).or_insert(7);
+
+    let keys: Vec<_> = map.iter_insertion_order().map(|(k, _)| k).collect();
+    assert_eq!(keys, [0, 2, 4, 3, 1, 7]);
+    let values: Vec<_> = map.iter_insertion_order().rev().map(|(_, v)| *v).collect();
+    assert_eq!(values, [7, 6, 5, 4, 2, 0]);
+
+    map.remove(7);
+    map.shrink_to_fit();
+    let clone = map.clone();
+    let values: Vec<_> = clone.iter_insertion_order().map(|(_, v)| *v).collect();
+    assert_eq!(values, [0, 2, 4, 5, 6]);
+
+    map.clear();
+    map.insert(8);
+    map.insert(9);
+    assert_eq!(map.iter_insertion_order().len(), 2);
+
+    // Without tracking, entries are visited in key order.
+    let mut map = IM::new();
+    let a = map.insert(0);
+    map.insert(1);
+    map.remove(a);
+    map.insert(2);
+    let values: Vec<_> = map.iter_insertion_order().map(|(_, v)| *v).collect();
+    assert_eq!(values, [2, 1]);
+}
+
+#[test]
+fn test_try_insert_mut() {
+    let mut map = IM::new();
+    for i in 0..3 {
+        map.insert(i);
+    }
+    map.remove(1);
+
+    assert_eq!(
+        map.try_insert_mut(|| Err::<i32, _>("failed")),
+        Err("failed")
+    );
+    assert_eq!(map.len(), 2);
+    assert!(!map.contains_key(1));
+
+    let (key, val) = map.try_insert_mut(|| Ok::<_, ()>(10)).unwrap();
+    assert_eq!(key, 1);
+    *val += 1;
+    assert_eq!(map[1], 11);
+
+    assert_eq!(
+        map.try_insert_mut(|| Ok::<_, ()>(20)).unwrap(),
+        (3, &mut 20)
+    );
+    assert_eq!(map.len(), 4);
+}
+
+#[test]
+fn test_iter_filter() {
+    let mut map = IM::new();
+    for i in 0..10 {
+        map.insert(i);
+    }
+    map.remove(4);
+    map.remove(6);
+
+    let keys: Vec<_> = map.iter_filter(|v| v % 2 == 0).map(|(k, _)| k).collect();
+    assert_eq!(keys, [0, 2, 8]);
+
+    map.iter_filter_mut(|v| *v > 6).for_each(|(_, v)| *v = 0);
+    let values: Vec<_> = map.values().copied().collect();
+    assert_eq!(values, [0, 1, 2, 3, 5, 0, 0, 0]);
+}
+
+#[test]
+fn test_try_insert_at() {
+    let mut map = IM::new();
+    map.insert(0);
+    map.insert(1);
+    map.remove(0);
+
+    assert_eq!(map.try_insert_at(1, 10), Ok(Some(1)));
+    assert_eq!(map.try_insert_at(0, 20), Ok(None));
+    assert_eq!(map.try_insert_at(3, 30), Ok(None));
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.insert(40), 2);
+
+    let capacity = map.capacity();
+    assert_eq!(map.try_insert_at(usize::MAX, 50), Err((usize::MAX, 50)));
+    assert_eq!(
+        map.try_insert_at(usize::MAX / 8, 60),
+        Err((usize::MAX / 8, 60))
+    );
+    assert_eq!(map.capacity(), capacity);
+    assert_eq!(map.len(), 4);
+    assert_eq!(map.insert(70), 4);
+}
+
+#[test]
+fn test_try_insert_at_insertion_order() {
+    let mut map = IM::with_insertion_order_tracking();
+    for i in 0..3 {
+        map.insert(i);
+    }
+    map.pop_front_swap();
+
+    assert_eq!(map.try_insert_at(5, 9), Ok(None));
+    let values: Vec<_> = map.iter_insertion_order().map(|(_, v)| *v).collect();
+    assert_eq!(values, [1, 2, 9]);
+
+    // Filling a padding slot records its insertion as well.
+    assert_eq!(map.try_insert_at(3, 10), Ok(None));
+    assert_eq!(map.try_insert_at(usize::MAX, 11), Err((usize::MAX, 11)));
+    let values: Vec<_> = map.iter_insertion_order().map(|(_, v)| *v).collect();
+    assert_eq!(values, [1, 2, 9, 10]);
+}
+
+#[test]
+fn test_resize() {
+    let mut map = IM::new();
+    for i in 0..6 {
+        map.insert(i);
+    }
+    map.remove(1);
+    map.remove(3);
+    map.remove(5);
+
+    map.resize(4, 10);
+    assert_eq!(map.len(), 5);
+    assert_eq!(map.values().copied().collect::<Vec<_>>(), [0, 10, 2, 10, 4]);
+
+    // Only the free slot past `new_len` is left to be reused.
+    assert_eq!(map.insert(20), 5);
+    assert_eq!(map.insert(30), 6);
+
+    map.resize(9, 40);
+    assert_eq!(map.len(), 9);
+    assert_eq!(map[8], 40);
+
+    map.resize(2, 50);
+    assert_eq!(map.len(), 9);
+    assert_eq!(map[8], 40);
+}
+
+#[test]
+fn test_resize_panic() {
+    use std::cell::Cell;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    thread_local! { static CLONES: Cell<usize> = const { Cell::new(0) } }
+
+    #[derive(Debug, PartialEq)]
+    struct PanicOnClone(usize);
+
+    impl Clone for PanicOnClone {
+        fn clone(&self) -> Self {
+            if CLONES.with(|c| c.replace(c.get() + 1)) == 2 {
+                panic!("clone panicked");
+            }
+            PanicOnClone(self.0)
+        }
+    }
+
+    let mut map = IM::new();
+    for i in 0..6 {
+        map.insert(PanicOnClone(i));
+    }
+    map.remove(1);
+    map.remove(3);
+    map.remove(4);
+
+    // The first two free slots get filled, then the third clone panics.
+    let res = catch_unwind(AssertUnwindSafe(|| map.resize(8, PanicOnClone(10))));
+    assert!(res.is_err());
+    assert_eq!(map.len(), 5);
+    assert_eq!(map.len(), map.iter().count());
+
+    let mut keys: Vec<_> = (0..3).map(|i| map.insert(PanicOnClone(20 + i))).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, [4, 6, 7]);
+    assert_eq!(map.len(), 8);
+    assert_eq!(map.len(), map.iter().count());
+}
+
+#[test]
+fn test_pop_front_swap() {
+    let mut map = IM::new();
+    for i in 0..6 {
+        map.insert(i);
+    }
+    map.remove(0);
+    map.remove(5);
+
+    assert_eq!(map.pop_front_swap(), Some((1, 1, Some(4))));
+    assert_eq!(map.values().copied().collect::<Vec<_>>(), [4, 2, 3]);
+    assert_eq!(map.key_bounds(), Some((1, 3)));
+
+    assert_eq!(map.pop_front_swap(), Some((1, 4, Some(3))));
+    assert_eq!(map.pop_front_swap(), Some((1, 3, Some(2))));
+    assert_eq!(map.pop_front_swap(), Some((1, 2, None)));
+    assert_eq!(map.pop_front_swap(), None);
+    assert!(map.is_empty());
+
+    // The free slots are still usable after all that.
+    let mut keys: Vec<_> = (0..6).map(|i| map.insert(i)).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, [0, 1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn test_partition() {
+    let mut map = IM::new();
+    for i in 0..8 {
+        map.insert(i);
+    }
+    map.remove(2);
+    map.remove(5);
+
+    let (small, mut large) = map.partition(|_, v| *v < 4);
+    assert_eq!(small.iter().map(|(k, _)| k).collect::<Vec<_>>(), [0, 1, 3]);
+    assert_eq!(large.iter().map(|(k, _)| k).collect::<Vec<_>>(), [4, 6, 7]);
+    for (k, v) in small.iter().chain(large.iter()) {
+        assert_eq!(k, *v);
+    }
+
+    // Free slots in each half are reusable.
+    let mut keys: Vec<_> = (0..6).map(|_| large.insert(0)).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, [0, 1, 2, 3, 5, 8]);
+}
+
+#[test]
+fn test_get_key_value_mut_or() {
+    let mut map = IM::new();
+    map.insert(1);
+    map.insert(2);
+    map.remove(0);
+
+    assert_eq!(map.get_key_value_mut_or(0, |_, v| *v), None);
+    assert_eq!(
+        map.get_key_value_mut_or(1, |k, v| {
+            *v += k * 10;
+            *v
+        }),
+        Some(12)
+    );
+    assert_eq!(map[1], 12);
+}
+
+thread_local! { static DROP_ORDER: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) } }
+
+struct DropOrder(usize);
+
+impl Drop for DropOrder {
+    fn drop(&mut self) {
+        DROP_ORDER.with(|v| v.borrow_mut().push(self.0));
+    }
+}
+
+#[test]
+fn test_clear_ordered() {
+    let mut map = IM::new();
+    for i in 0..6 {
+        map.insert(DropOrder(i));
+    }
+    map.remove(1);
+    map.remove(4);
+    map.insert(DropOrder(10));
+
+    DROP_ORDER.with(|v| v.borrow_mut().clear());
+    map.clear_ordered();
+    DROP_ORDER.with(|v| assert_eq!(*v.borrow(), [0, 2, 3, 10, 5]));
+
+    assert!(map.is_empty());
+    assert_eq!(map.insert(DropOrder(20)), 0);
+}
+
+#[test]
+fn test_clear_ordered_panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    struct PanicOnDrop(bool);
+
+    impl Drop for PanicOnDrop {
+        fn drop(&mut self) {
+            if self.0 {
+                panic!("dropping a value panicked");
+            }
+        }
+    }
+
+    let mut map = IM::with_insertion_order_tracking();
+    map.insert(PanicOnDrop(false));
+    map.insert(PanicOnDrop(true));
+    map.insert(PanicOnDrop(false));
+    map.remove(0);
+
+    let res = catch_unwind(AssertUnwindSafe(|| map.clear_ordered()));
+    assert!(res.is_err());
+
+    // The map is left empty, not holding the values after the panicking one.
+    assert!(map.is_empty());
+    assert_eq!(map.iter().count(), 0);
+    assert_eq!(map.insert(PanicOnDrop(false)), 0);
+    assert_eq!(map.insert(PanicOnDrop(false)), 1);
+}
+
+#[test]
+fn test_retain_ref() {
+    use std::cell::Cell;
+
+    let mut map = IM::new();
+    for i in 0..10 {
+        map.insert(Cell::new(i));
+    }
+
+    let seen = Cell::new(0);
+    map.retain_ref(|k, v| {
+        seen.set(seen.get() + 1);
+        v.get() % 3 != 0 && k != 4
+    });
+    assert_eq!(seen.get(), 10);
+    assert_eq!(map.keys().collect::<Vec<_>>(), [1, 2, 5, 7, 8]);
+    assert_eq!(map.insert(Cell::new(0)), 9);
+}
+
+#[test]
+fn test_reset_to() {
+    let mut map = IM::new();
+    for i in 0..100 {
+        map.insert(i);
+    }
+    map.remove(10);
+    map.remove(50);
+    let capacity = map.capacity();
+
+    map.reset_to(0..20);
+    assert_eq!(map.len(), 20);
+    assert_eq!(map.capacity(), capacity);
+    for (k, v) in &map {
+        assert_eq!(k, *v);
+    }
+    assert_eq!(map.insert(20), 20);
+}
+
+#[test]
+fn test_find_map_value() {
+    let mut map = IM::new();
+    for i in 0..10 {
+        map.insert(i);
+    }
+    map.remove(3);
+
+    let mut calls = 0;
+    let found = map.find_map_value(|k, v| {
+        calls += 1;
+        if *v >= 3 {
+            Some(k * 100)
+        } else {
+            None
+        }
+    });
+    assert_eq!(found, Some(400));
+    assert_eq!(calls, 4);
+    assert_eq!(
+        map.find_map_value(|_, v| if *v > 9 { Some(()) } else { None }),
+        None
+    );
+}
+
+#[test]
+fn test_append() {
+    let mut a = IM::new();
+    for i in 0..4 {
+        a.insert(i);
+    }
+    a.remove(1);
+
+    let mut b = IM::new();
+    for i in 10..14 {
+        b.insert(i);
+    }
+    b.remove(0);
+
+    a.append(&mut b);
+    assert_eq!(a.len(), 6);
+    assert_eq!(
+        a.values().copied().collect::<Vec<_>>(),
+        [0, 11, 2, 3, 12, 13]
+    );
+
+    assert!(b.is_empty());
+    assert_eq!(b.insert(20), 0);
+}
+
+#[test]
+fn test_collect_into() {
+    let mut map = IM::new();
+    for i in 0..10 {
+        map.insert(i * 2);
+    }
+    map.remove(3);
+    map.remove(7);
+
+    let mut keys = Vec::with_capacity(32);
+    let mut values = vec![100; 20];
+    map.collect_keys_into(&mut keys);
+    map.collect_values_into(&mut values);
+    assert_eq!(keys, [0, 1, 2, 4, 5, 6, 8, 9]);
+    assert_eq!(values, [0, 2, 4, 8, 10, 12, 16, 18]);
+    assert_eq!(keys.capacity(), 32);
+    assert!(values.capacity() >= 20);
+
+    map.clear();
+    map.collect_keys_into(&mut keys);
+    assert!(keys.is_empty());
+}
+
+#[test]
+fn test_for_each_window_mut() {
+    let mut map = IM::new();
+    for i in 0..8 {
+        map.insert(i);
+    }
+    map.remove(0);
+    map.remove(4);
+
+    let mut windows = Vec::new();
+    map.for_each_window_mut(|[a, b, c]| {
+        windows.push([*a, *b, *c]);
+        *b = *a + *c;
+    });
+    assert_eq!(windows, [[1, 2, 3], [4, 3, 5], [9, 5, 6], [15, 6, 7]]);
+    assert_eq!(
+        map.values().copied().collect::<Vec<_>>(),
+        [1, 4, 9, 15, 22, 7]
+    );
+
+    let mut calls = 0;
+    map.for_each_window_mut::<7, _>(|_| calls += 1);
+    assert_eq!(calls, 0);
+    map.for_each_window_mut::<6, _>(|_| calls += 1);
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn test_remove_range() {
+    let mut map = IM::new();
+    for i in 0..10 {
+        map.insert(i);
+    }
+    map.remove(3);
+
+    assert_eq!(map.remove_range(2..=4), 2);
+    assert_eq!(map.len(), 7);
+    assert!(!map.contains_key(2));
+    assert!(!map.contains_key(4));
+
+    assert_eq!(map.remove_range(8..100), 2);
+    assert_eq!(map.remove_range(20..), 0);
+    assert_eq!(map.remove_range(..0), 0);
+    assert_eq!(map.keys().collect::<Vec<_>>(), [0, 1, 5, 6, 7]);
+
+    // All the removed keys are reused before the map grows
+    let mut reused = (0..5).map(|i| map.insert(i)).collect::<Vec<_>>();
+    reused.sort_unstable();
+    assert_eq!(reused, [2, 3, 4, 8, 9]);
+    assert_eq!(map.insert(0), 10);
+
+    assert_eq!(map.remove_range(..), 11);
+    assert!(map.is_empty());
+}
+
+#[test]
+fn test_into_range_drops() {
+    DROP_VECTOR.with(|v| {
+        *v.borrow_mut() = vec![0; 100];
+    });
+
+    let mut m = IM::new();
+    for i in 0..100 {
+        m.insert(Droppable::new(i));
+    }
+    m.remove(50);
+
+    {
+        let mut page = m.into_range(40..60);
+        assert_eq!(page.len(), 19);
+
+        DROP_VECTOR.with(|v| {
+            let v = v.borrow();
+            for i in 0..100 {
+                let expected = if (40..60).contains(&i) && i != 50 {
+                    1
+                } else {
+                    0
+                };
+                assert_eq!(v[i], expected);
+            }
+        });
+
+        let (k, d) = page.next_back().unwrap();
+        assert_eq!((k, d.k), (59, 59));
+        let (k, d) = page.next().unwrap();
+        assert_eq!((k, d.k), (40, 40));
+
+        let keys: Vec<_> = page.by_ref().rev().take(10).map(|(k, _)| k).collect();
+        assert_eq!(keys, [58, 57, 56, 55, 54, 53, 52, 51, 49, 48]);
+        assert_eq!(page.len(), 7);
+    }
+
+    DROP_VECTOR.with(|v| {
+        for i in 0..100 {
+            assert_eq!(v.borrow()[i], 0);
+        }
+    });
+}
+
+#[test]
+fn test_fold() {
+    let mut map = IM::new();
+    for i in 0..20i64 {
+        map.insert(i);
+    }
+    for i in (0..20).step_by(3) {
+        map.remove(i);
+    }
+    for i in 15..20 {
+        map.remove(i);
+    }
+
+    let expected: i64 = (0..15).filter(|i| i % 3 != 0).sum();
+    assert_eq!(map.values().sum::<i64>(), expected);
+    assert_eq!(map.keys().sum::<usize>(), expected as usize);
+    assert_eq!(
+        map.iter().map(|(_, v)| v).product::<i64>(),
+        2 * 4 * 5 * 7 * 8 * 10 * 11 * 13 * 14
+    );
+
+    // Folding a partially consumed iterator only visits what's left.
+    let mut values = map.values();
+    values.next();
+    values.next();
+    assert_eq!(
+        values.fold(Vec::new(), |mut acc, v| {
+            acc.push(*v);
+            acc
+        }),
+        [4, 5, 7, 8, 10, 11, 13, 14]
+    );
+}
+
+#[test]
+fn test_insert_at_if_free() {
+    let mut map = IM::new();
+    let a = map.insert("a");
+    let b = map.insert("b");
+    map.remove(a);
+
+    assert_eq!(map.insert_at_if_free(b, "x"), Err("x"));
+    assert_eq!(map[b], "b");
+
+    assert_eq!(map.insert_at_if_free(a, "c"), Ok(()));
+    assert_eq!(map.insert_at_if_free(a, "x"), Err("x"));
+    assert_eq!(map[a], "c");
+
+    // Padding slots past the end are free, and get handed out by `insert`.
+    assert_eq!(map.insert_at_if_free(5, "d"), Ok(()));
+    assert_eq!(map.len(), 3);
+    let mut keys = (0..3).map(|_| map.insert("e")).collect::<Vec<_>>();
+    keys.sort_unstable();
+    assert_eq!(keys, [2, 3, 4]);
+    assert_eq!(map.insert("f"), 6);
+}
+
+#[test]
+fn test_builder() {
+    let mut map = IM::builder().build();
+    assert_eq!(map.capacity(), 0);
+    map.insert(1);
+    assert_eq!(map[0], 1);
+
+    let mut map = IM::builder().capacity(8).track_insertion_order().build();
+    assert!(map.capacity() >= 8);
+    map.insert_at_if_free(3, 'a').unwrap();
+    map.insert('b');
+    map.insert('c');
+    let values: Vec<_> = map.iter_insertion_order().map(|(_, v)| *v).collect();
+    assert_eq!(values, ['a', 'b', 'c']);
+    assert_eq!(map.keys().collect::<Vec<_>>(), [1, 2, 3]);
+}
+
+#[test]
+fn test_get_disjoint_mut_report() {
+    use index_map::DisjointError::{Absent, Duplicate};
+
+    let mut map = IM::new();
+    for i in 0..6 {
+        map.insert(i * 10);
+    }
+    map.remove(2);
+
+    let [a, b, c, d, e] = map.get_disjoint_mut_report([5, 2, 0, 5, 100]);
+    assert_eq!(b, Err(Absent));
+    assert_eq!(d, Err(Duplicate));
+    assert_eq!(e, Err(Absent));
+    let (a, c) = (a.unwrap(), c.unwrap());
+    *a += 1;
+    *c += 2;
+    assert_eq!(map[5], 51);
+    assert_eq!(map[0], 2);
+
+    // A duplicate of an absent key is still reported as a duplicate.
+    assert_eq!(
+        map.get_disjoint_mut_report([2, 2]),
+        [Err(Absent), Err(Duplicate)]
+    );
+    assert_eq!(map.get_disjoint_mut_report::<usize, 0>([]), []);
+
+    let keys = [4, 3, 1];
+    for (key, val) in keys.iter().zip(map.get_disjoint_mut_report(keys)) {
+        assert_eq!(*val.unwrap(), key * 10);
+    }
+}
+
+#[test]
+fn test_transform() {
+    let mut map = IM::new();
+    for i in 0..8 {
+        map.insert(i);
+    }
+    map.remove(3);
+    map.remove(6);
+
+    let mut seen = Vec::new();
+    map.transform(|key, val| {
+        seen.push(key);
+        if val % 2 == 0 {
+            None
+        } else {
+            Some(val * 10)
+        }
+    });
+    assert_eq!(seen, [0, 1, 2, 4, 5, 7]);
+    assert_eq!(map.len(), 3);
+    assert_eq!(
+        map.iter().map(|(k, v)| (k, *v)).collect::<Vec<_>>(),
+        [(1, 10), (5, 50), (7, 70)]
+    );
+
+    // Every freed key is handed out again before the map grows.
+    let mut keys = (0..5).map(|i| map.insert(i)).collect::<Vec<_>>();
+    keys.sort_unstable();
+    assert_eq!(keys, [0, 2, 3, 4, 6]);
+    assert_eq!(map.insert(0), 8);
+}
+
+#[test]
+fn test_transform_panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut map = IM::new();
+    for i in 0..4 {
+        map.insert(i);
+    }
+
+    let res = catch_unwind(AssertUnwindSafe(|| {
+        map.transform(|key, val| {
+            if key == 2 {
+                panic!("transform panicked");
+            }
+            Some(val)
+        })
+    }));
+    assert!(res.is_err());
+
+    // The value being transformed is lost, but the map is still consistent.
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.keys().collect::<Vec<_>>(), [0, 1, 3]);
+    assert_eq!(map.insert(2), 2);
+    assert_eq!(map.insert(4), 4);
+}
+
+#[test]
+fn test_clone_with_capacity() {
+    let mut map = IM::new();
+    for i in 0..10 {
+        map.insert(i);
+    }
+    map.remove(4);
+    map.remove(7);
+
+    let mut clone = map.clone_with_capacity(64);
+    assert!(clone.capacity() >= 64);
+    assert_eq!(clone, map);
+
+    // The clone shares the free list layout, so both hand out the same keys.
+    assert_eq!(clone.insert(0), map.insert(0));
+    assert_eq!(clone.insert(0), map.insert(0));
+    assert_eq!(clone.insert(0), 10);
+
+    let small = map.clone_with_capacity(0);
+    assert!(small.capacity() >= 10);
+    assert_eq!(small, map);
+}
+
+#[test]
+fn test_iterate_keys_values_by_ref() {
+    let mut map = IM::new();
+    for i in 0..5 {
+        map.insert(i * 2);
+    }
+    map.remove(1);
+
+    let mut keys = map.keys();
+    keys.next();
+    let mut sum = 0;
+    for k in &keys {
+        sum += k;
+    }
+    for k in &keys {
+        sum += k;
+    }
+    assert_eq!(sum, 2 * (2 + 3 + 4));
+    assert_eq!(keys.len(), 3);
+
+    let values = map.values();
+    let collected: Vec<_> = (&values).into_iter().copied().collect();
+    assert_eq!(collected, [0, 4, 6, 8]);
+    assert_eq!(values.copied().collect::<Vec<_>>(), collected);
+}
+
+#[test]
+fn test_drain_resets_free_list() {
+    let mut map = IM::with_capacity(16);
+    for i in 0..10 {
+        map.insert(i);
+    }
+    for i in (0..10).step_by(3) {
+        map.remove(i);
+    }
+
+    {
+        let mut drain = map.drain();
+        assert_eq!(drain.len(), 6);
+        assert_eq!(drain.next(), Some((1, 1)));
+    }
+
+    assert!(map.is_empty());
+    assert!(map.capacity() >= 16);
+    for i in 0..5 {
+        assert_eq!(map.insert(i), i);
+    }
+}
+
+#[test]
+fn test_handle() {
+    let mut map = IM::new();
+    let a = map.insert_handle(1);
+    let b = map.insert_handle(2);
+    assert_ne!(a, b);
+    assert_eq!(usize::from(b), 1);
+
+    *map.get_mut(b).unwrap() += 10;
+    assert_eq!(map.get(b), Some(&12));
+    assert_eq!(b.resolve(&map), Some(&12));
+
+    // Handles survive the map growing.
+    for i in 0..100 {
+        map.insert(i);
+    }
+    *a.resolve_mut(&mut map).unwrap() *= 5;
+    assert_eq!(map[a.key()], 5);
+
+    map.remove(a.key());
+    assert_eq!(a.resolve(&map), None);
+    assert_eq!(map.get_mut(a), None);
+
+    // Plain keys, including integer literals, still work.
+    assert_eq!(map.get(1), Some(&12));
+    assert_eq!(map.get(2usize), Some(&0));
+}
+
+#[test]
+fn test_count_range() {
+    let mut map = IM::new();
+    assert_eq!(map.count_range(..), 0);
+
+    for i in 0..20 {
+        map.insert(i);
+    }
+    for i in (0..20).step_by(4) {
+        map.remove(i);
+    }
+
+    assert_eq!(map.count_range(..), map.len());
+    assert_eq!(map.count_range(0..4), 3);
+    assert_eq!(map.count_range(4..=8), 3);
+    assert_eq!(map.count_range(18..100), 2);
+    assert_eq!(map.count_range(50..), 0);
+    assert_eq!(map.count_range(5..5), 0);
+    for start in 0..22 {
+        for end in start..22 {
+            assert_eq!(
+                map.count_range(start..end),
+                map.keys().filter(|k| (start..end).contains(k)).count()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_compact_with() {
+    let mut map = IM::with_insertion_order_tracking();
+    for i in 0..12 {
+        map.insert(i);
+    }
+    for &i in &[0, 3, 4, 9, 11] {
+        map.remove(i);
+    }
+    let before: std::collections::HashMap<_, _> = map.iter().map(|(k, v)| (k, *v)).collect();
+
+    let mut moves = Vec::new();
+    map.compact_with(|old, new, val| {
+        assert_eq!(before[&old], *val);
+        moves.push((old, new));
+    });
+    assert_eq!(moves, [(10, 0), (8, 3), (7, 4)]);
+    assert!(map.is_dense());
+    assert_eq!(map.len(), 7);
+    assert_eq!(
+        map.values().copied().collect::<Vec<_>>(),
+        [10, 1, 2, 8, 7, 5, 6]
+    );
+
+    // Insertion order follows the values around.
+    let order: Vec<_> = map.iter_insertion_order().map(|(_, v)| *v).collect();
+    assert_eq!(order, [1, 2, 5, 6, 7, 8, 10]);
+    assert_eq!(map.insert(20), 7);
+
+    // Compacting a dense or an empty map moves nothing.
+    map.compact_with(|_, _, _| panic!("nothing should move"));
+    map.retain(|_, _| false);
+    map.compact_with(|_, _, _| panic!("nothing should move"));
+    assert!(map.is_empty());
+    assert_eq!(map.insert(0), 0);
+}
+
+#[test]
+fn test_compact_with_panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut map = IM::new();
+    for i in 0..8 {
+        map.insert(i);
+    }
+    for i in 0..4 {
+        map.remove(i);
+    }
+
+    let res = catch_unwind(AssertUnwindSafe(|| {
+        map.compact_with(|old, _, _| {
+            if old == 6 {
+                panic!("compact_with panicked");
+            }
+        })
+    }));
+    assert!(res.is_err());
+
+    // 7 and 6 have moved, and the remaining free slots are still handed out.
+    assert_eq!(map.len(), 4);
+    assert_eq!(map.keys().collect::<Vec<_>>(), [0, 1, 4, 5]);
+    let mut keys = vec![map.insert(0), map.insert(0)];
+    keys.sort_unstable();
+    assert_eq!(keys, [2, 3]);
+    assert_eq!(map.insert(0), 6);
+}
+
+#[test]
+fn test_from_fn() {
+    let map = IM::from_fn(5, |k| k * 10);
+    assert!(map.is_dense());
+    assert_eq!(map.len(), 5);
+    assert_eq!(map.capacity(), 5);
+    assert_eq!(
+        map.iter().map(|(k, v)| (k, *v)).collect::<Vec<_>>(),
+        [(0, 0), (1, 10), (2, 20), (3, 30), (4, 40)]
+    );
+
+    let mut map = IM::from_fn(0, |_| unreachable!());
+    assert!(map.is_empty());
+    assert_eq!(map.insert(1), 0);
+}
+
+#[test]
+fn test_retain_with() {
+    let mut map = IM::new();
+    for i in 0..10 {
+        map.insert(i % 4);
+    }
+    map.remove(1);
+
+    // Keep at most two entries with each value.
+    let mut counts = [0; 4];
+    let mut visited = Vec::new();
+    map.retain_with(&mut counts, |counts, key, val| {
+        visited.push(key);
+        counts[*val] += 1;
+        counts[*val] <= 2
+    });
+
+    assert_eq!(visited, [0, 2, 3, 4, 5, 6, 7, 8, 9]);
+    assert_eq!(counts, [3, 2, 2, 2]);
+    assert_eq!(map.keys().collect::<Vec<_>>(), [0, 2, 3, 4, 5, 6, 7, 9]);
+}
+
+#[test]
+fn test_split_at_key_mut() {
+    let mut map = IM::new();
+    for i in 0..10 {
+        map.insert(i);
+    }
+    map.remove(2);
+    map.remove(7);
+
+    {
+        let (low, high) = map.split_at_key_mut(5);
+        assert_eq!(low.len(), 4);
+        assert_eq!(high.len(), 4);
+
+        let low: Vec<_> = low.collect();
+        let high: Vec<_> = high.collect();
+        assert_eq!(
+            low.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+            [0, 1, 3, 4]
+        );
+        assert_eq!(
+            high.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+            [5, 6, 8, 9]
+        );
+        for ((_, lo), (_, hi)) in low.into_iter().zip(high) {
+            std::mem::swap(lo, hi);
+        }
+    }
+    assert_eq!(
+        map.values().copied().collect::<Vec<_>>(),
+        [5, 6, 8, 9, 0, 1, 3, 4]
+    );
+
+    let (low, high) = map.split_at_key_mut(0);
+    assert_eq!(low.len(), 0);
+    assert_eq!(
+        high.map(|(k, _)| k).collect::<Vec<_>>(),
+        [0, 1, 3, 4, 5, 6, 8, 9]
+    );
+
+    let (low, high) = map.split_at_key_mut(100);
+    assert_eq!(low.len(), 8);
+    assert_eq!(high.count(), 0);
+}
+
+#[test]
+fn test_with_capacity_dense_fill() {
+    for &n in &[0, 1, 2, 7, 64, 1000] {
+        let mut map = IM::with_capacity(n);
+        let capacity = map.capacity();
+        assert!(capacity >= n);
+
+        for i in 0..n {
+            assert_eq!(map.insert(i), i);
+        }
+        assert_eq!(map.capacity(), capacity);
+        assert!(map.is_dense());
+    }
+}
+
+#[test]
+fn test_split_one_mut() {
+    let mut map = IM::new();
+    for i in 0..6 {
+        map.insert(i * 10);
+    }
+    map.remove(1);
+
+    assert!(map.split_one_mut(1).is_none());
+    assert!(map.split_one_mut(6).is_none());
+
+    let (val, rest) = map.split_one_mut(3).unwrap();
+    assert_eq!(*val, 30);
+    assert_eq!(rest.key(), 3);
+    assert_eq!(rest.get(0), Some(&0));
+    assert_eq!(rest.get(1), None);
+    assert_eq!(rest.get(3), None);
+    assert_eq!(rest.get(5), Some(&50));
+    assert_eq!(rest.get(6), None);
+    assert_eq!(
+        rest.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+        [0, 2, 4, 5]
+    );
+    *val = rest.get(2).unwrap() + rest.get(4).unwrap();
+    assert_eq!(map[3], 60);
+
+    // The first and last keys have nothing on one side.
+    let (val, rest) = map.split_one_mut(0).unwrap();
+    *val = rest.iter().count() as i32;
+    let (_, rest) = map.split_one_mut(5).unwrap();
+    assert_eq!(rest.get(0), Some(&4));
+    assert_eq!(format!("{:?}", rest), "{0: 4, 2: 20, 3: 60, 4: 40}");
+}
+
+#[test]
+fn test_retain_compacting() {
+    let mut map = IM::with_insertion_order_tracking();
+    for i in 0..10 {
+        map.insert(i);
+    }
+    map.remove(0);
+    map.remove(5);
+
+    let moved = map.retain_compacting(|_, v| {
+        *v *= 10;
+        *v % 30 != 0
+    });
+    assert_eq!(moved, [(1, 0), (2, 1), (4, 2), (7, 3), (8, 4)]);
+    assert_eq!(
+        map.iter().map(|(k, v)| (k, *v)).collect::<Vec<_>>(),
+        [(0, 10), (1, 20), (2, 40), (3, 70), (4, 80)]
+    );
+    assert!(map.is_dense());
+    assert_eq!(map.insert(0), 5);
+
+    let order: Vec<_> = map.iter_insertion_order().map(|(_, v)| *v).collect();
+    assert_eq!(order, [10, 20, 40, 70, 80, 0]);
+
+    // Nothing moves if the surviving entries are already packed.
+    assert!(map.retain_compacting(|k, _| k < 3).is_empty());
+    assert_eq!(map.len(), 3);
+    assert!(map.retain_compacting(|_, _| false).is_empty());
+    assert!(map.is_empty());
+    assert_eq!(map.insert(0), 0);
+
+    assert_eq!(map.try_insert_at(3, 1), Ok(None));
+    map.insert(2);
+    let order: Vec<_> = map.iter_insertion_order().map(|(_, v)| *v).collect();
+    assert_eq!(order, [0, 1, 2]);
+}
+
+#[test]
+fn test_get_cloned() {
+    let mut map = IndexMap::new();
+    let a = map.insert(vec![1, 2]);
+    let b = map.insert(vec![3]);
+    map.remove(b);
+
+    let mut cloned = map.get_cloned(a).unwrap();
+    cloned.push(3);
+    assert_eq!(map[a], [1, 2]);
+    assert_eq!(map.get_cloned(b), None);
+    assert_eq!(map.get_key_value_cloned(a), Some((a, vec![1, 2])));
+    assert_eq!(map.get_key_value_cloned(b), None);
+}
+
+#[test]
+fn test_debug_alternate() {
+    let mut map = IM::new();
+    for i in 0..5 {
+        map.insert(i);
+    }
+    map.remove(1);
+    map.remove(3);
+
+    assert_eq!(format!("{:?}", map), "{0: 0, 2: 2, 4: 4}");
+    assert_eq!(
+        format!("{:#?}", map),
+        "{
+    0: 0,
+    2: 2,
+    4: 4,
+    __free_list: FreeList {
+        head: Some(
+            3,
+        ),
+        chain: [
+            3,
+            1,
+        ],
+    },
+}"
+    );
+
+    let empty: IM<i32> = IM::new();
+    assert_eq!(
+        format!("{:#?}", empty),
+        "{
+    __free_list: FreeList {
+        head: None,
+        chain: [],
+    },
+}"
+    );
+}
+
+#[test]
+fn test_append_preserving() {
+    use index_map::KeyCollision;
+
+    let mut a = IM::new();
+    let mut b = IM::new();
+    for i in 0..10 {
+        a.insert(i);
+        b.insert(i + 100);
+    }
+    a.retain(|k, _| k % 2 == 0);
+    b.retain(|k, _| k % 2 == 1);
+    b.insert_at_if_free(13, 113).unwrap();
+
+    let mut c = b.clone();
+    c.insert_at_if_free(4, 0).unwrap();
+    c.insert_at_if_free(2, 0).unwrap();
+    let c_keys: Vec<_> = c.keys().collect();
+    assert_eq!(a.append_preserving(&mut c), Err(KeyCollision { key: 2 }));
+    assert_eq!(a.len(), 5);
+    assert_eq!(c.keys().collect::<Vec<_>>(), c_keys);
+
+    assert_eq!(a.append_preserving(&mut b), Ok(()));
+    assert!(b.is_empty());
+    assert_eq!(b.insert(0), 0);
+    assert_eq!(a.len(), 11);
+    for k in 0..10 {
+        assert_eq!(a[k], if k % 2 == 0 { k } else { k + 100 });
+    }
+    assert_eq!(a[13], 113);
+
+    // The padding between the old end and the new keys is free.
+    assert_eq!(a.insert(10), 10);
+    assert_eq!(a.insert(11), 11);
+    assert_eq!(a.insert(12), 12);
+    assert_eq!(a.insert(14), 14);
+}
+
+#[test]
+fn test_short_circuiting() {
+    let mut map = IM::new();
+    for i in 0..100 {
+        map.insert(i);
+    }
+    for i in 10..100 {
+        if i != 50 {
+            map.remove(i);
+        }
+    }
+
+    assert!(map.iter().any(|(_, v)| *v == 50));
+    assert!(map.values().all(|v| *v < 51));
+    assert_eq!(map.iter().find(|(_, v)| **v > 5), Some((6, &6)));
+    assert_eq!(map.keys().position(|k| k == 50), Some(10));
+
+    let mut iter = map.clone().into_iter();
+    assert_eq!(iter.find(|(_, v)| *v == 50), Some((50, 50)));
+    assert_eq!(iter.len(), 0);
+    assert_eq!(iter.next(), None);
+
+    let mut drain = map.drain();
+    assert!(!drain.any(|(_, v)| v > 50));
+    assert_eq!(drain.next(), None);
+}
+
+#[test]
+fn test_get2_get3_mut() {
+    let mut map = IM::new();
+    for i in 0..5 {
+        map.insert(i);
+    }
+    map.remove(2);
+
+    let (a, b) = map.get2_mut(4, 0).unwrap();
+    *a += 10;
+    *b += 20;
+    assert_eq!(map[4], 14);
+    assert_eq!(map[0], 20);
+    assert!(map.get2_mut(1, 2).is_none());
+    assert!(map.get2_mut(3, 3).is_none());
+
+    let (a, b, c) = map.get3_mut(3, 1, 4).unwrap();
+    std::mem::swap(a, c);
+    *b = 0;
+    assert_eq!(map.values().copied().collect::<Vec<_>>(), [20, 0, 14, 3]);
+    assert!(map.get3_mut(0, 1, 2).is_none());
+    assert!(map.get3_mut(0, 1, 0).is_none());
+    assert!(map.get3_mut(0, 1, 10).is_none());
+}
+
+#[test]
+fn test_last_key_values() {
+    let mut map = IM::new();
+    assert_eq!(map.last_key_values(3).next(), None);
+
+    for i in 0..10 {
+        map.insert(i * 10);
+    }
+    map.remove(9);
+    map.remove(7);
+
+    let last: Vec<_> = map.last_key_values(3).map(|(k, v)| (k, *v)).collect();
+    assert_eq!(last, [(8, 80), (6, 60), (5, 50)]);
+    assert_eq!(map.last_key_values(0).count(), 0);
+
+    let all: Vec<_> = map.last_key_values(usize::MAX).map(|(k, _)| k).collect();
+    let mut keys: Vec<_> = map.keys().collect();
+    keys.reverse();
+    assert_eq!(all, keys);
+}
+
+#[test]
+fn test_insert_batch_at() {
+    let mut map = IM::with_insertion_order_tracking();
+    map.insert(0);
+    map.insert(1);
+    map.remove(0);
+
+    map.insert_batch_at(vec![(6, 60), (1, 10), (3, 30), (6, 61)]);
+    assert_eq!(map.len(), 3);
+    assert_eq!(
+        map.iter().map(|(k, v)| (k, *v)).collect::<Vec<_>>(),
+        [(1, 10), (3, 30), (6, 61)]
+    );
+
+    let order: Vec<_> = map.iter_insertion_order().map(|(_, v)| *v).collect();
+    assert_eq!(order, [10, 30, 61]);
+
+    // Every other slot is free, and gets reused before the map grows.
+    let mut keys: Vec<_> = (0..4).map(|i| map.insert(i)).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, [0, 2, 4, 5]);
+    assert_eq!(map.insert(7), 7);
+
+    map.insert_batch_at(Vec::new());
+    assert_eq!(map.len(), 8);
+}
+
+#[test]
+fn test_insert_batch_at_drops() {
+    DROP_VECTOR.with(|v| {
+        *v.borrow_mut() = vec![0; 10];
+    });
+
+    {
+        let mut map = IM::new();
+        map.insert(Droppable::new(0));
+        map.insert_batch_at((0..10).rev().map(|i| (i, Droppable::new(i))));
+
+        DROP_VECTOR.with(|v| {
+            assert!(v.borrow().iter().all(|&n| n == 1));
+        });
+    }
+
+    DROP_VECTOR.with(|v| {
+        assert!(v.borrow().iter().all(|&n| n == 0));
+    });
+}
+
+#[test]
+fn test_is_key_free() {
+    let mut map = IM::new();
+    assert!(!map.is_key_free(0));
+    assert!(map.is_key_allocatable(0));
+
+    for i in 0..4 {
+        map.insert(i);
+    }
+    map.remove(1);
+    map.remove(3);
+
+    let free: Vec<_> = (0..6).filter(|&k| map.is_key_free(k)).collect();
+    let allocatable: Vec<_> = (0..6).filter(|&k| map.is_key_allocatable(k)).collect();
+    assert_eq!(free, [1, 3]);
+    assert_eq!(allocatable, [1, 3, 4, 5]);
+    assert!(map.is_key_allocatable(usize::MAX));
+
+    map.shrink_to_fit();
+    assert!(!map.is_key_free(3));
+    assert!(map.is_key_allocatable(3));
+}
+
+#[test]
+fn test_entry_or_insert_with_key_mut() {
+    let mut map = IM::new();
+    map.insert(String::from("a"));
+
+    let (key, val) = map
+        .entry(0)
+        .or_insert_with_key_mut(|_| unreachable!("entry is occupied"));
+    val.push('!');
+    assert_eq!(key, 0);
+
+    let (key, val) = map.entry(3).or_insert_with_key_mut(|key| key.to_string());
+    val.push('?');
+    assert_eq!(key, 3);
+
+    assert_eq!(map[0], "a!");
+    assert_eq!(map[3], "3?");
+    assert_eq!(map.len(), 2);
+}
+
+#[test]
+fn test_take_all() {
+    let mut map = IM::with_capacity(32);
+    for i in 0..20 {
+        map.insert(i);
+    }
+    map.retain(|k, _| k % 3 == 0);
+
+    let entries = map.take_all();
+    assert_eq!(entries.capacity(), 7);
+    assert_eq!(
+        entries,
+        (0..20).step_by(3).map(|i| (i, i)).collect::<Vec<_>>()
+    );
+    assert!(map.is_empty());
+    assert!(map.capacity() >= 32);
+    for i in 0..3 {
+        assert_eq!(map.insert(i), i);
+    }
+
+    assert_eq!(IM::<i32>::new().take_all(), []);
+}
+
+#[test]
+fn test_get_mut_or_insert_at() {
+    let mut map = IM::new();
+    map.insert(1);
+    map.insert(2);
+    map.remove(0);
+
+    *map.get_mut_or_insert_at(4, 40) += 1;
+    *map.get_mut_or_insert_at(1, 10) += 1;
+    *map.get_mut_or_insert_at(0, 0) += 1;
+    assert_eq!(
+        map.iter().map(|(k, v)| (k, *v)).collect::<Vec<_>>(),
+        [(0, 1), (1, 3), (4, 41)]
+    );
+
+    // The padding is free and reused by `insert`.
+    let mut keys = vec![map.insert(0), map.insert(0)];
+    keys.sort_unstable();
+    assert_eq!(keys, [2, 3]);
+    assert_eq!(map.insert(0), 5);
+}
+
+#[test]
+fn test_maybe_shrink() {
+    let mut map = IM::with_capacity(64);
+    for i in 0..32 {
+        map.insert(i);
+    }
+    let cap = map.capacity();
+
+    // Half full is above the threshold, so nothing happens.
+    map.maybe_shrink(0.25);
+    assert_eq!(map.capacity(), cap);
+
+    map.retain(|k, _| k < 8);
+    map.maybe_shrink(0.25);
+    assert_eq!(map.capacity(), 8);
+
+    // Already tight: 8 / 8 is not below the factor.
+    map.maybe_shrink(0.25);
+    assert_eq!(map.capacity(), 8);
+    assert_eq!(map.keys().collect::<Vec<_>>(), (0..8).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_iter_count() {
+    let mut map = IM::new();
+    for i in 0..100 {
+        map.insert(i);
+    }
+    map.retain(|k, _| k % 3 == 0);
+    assert_eq!(map.len(), 34);
+
+    assert_eq!(map.iter().count(), map.len());
+    assert_eq!(map.keys().count(), map.len());
+    assert_eq!(map.values().count(), map.len());
+
+    let mut iter = map.iter();
+    iter.next();
+    iter.next();
+    assert_eq!(iter.count(), map.len() - 2);
+
+    let mut keys = map.keys();
+    keys.next();
+    assert_eq!(keys.count(), map.len() - 1);
+
+    let mut into_iter = map.clone().into_iter();
+    into_iter.next_back();
+    assert_eq!(into_iter.count(), map.len() - 1);
+}
+
+#[test]
+fn test_get_disjoint_mut_handles() {
+    let mut map = IM::new();
+    let a = map.insert_handle(1);
+    let b = map.insert_handle(2);
+    let c = map.insert_handle(3);
+
+    let [x, y] = map.get_disjoint_mut_report([c, a]);
+    std::mem::swap(x.unwrap(), y.unwrap());
+    assert_eq!(map.values().copied().collect::<Vec<_>>(), [3, 2, 1]);
+
+    if let Some((x, y)) = map.get2_mut(a, b) {
+        *x += *y;
+    }
+    if let Some((x, y, z)) = map.get3_mut(a, b, c) {
+        *z = *x * *y;
+    }
+    assert_eq!(map.values().copied().collect::<Vec<_>>(), [5, 2, 10]);
+    assert!(map.get2_mut(b, b).is_none());
+
+    // Plain keys keep working, literals included.
+    assert!(map.get3_mut(0, 1, 2).is_some());
+    assert!(map.get2_mut(0, 3).is_none());
+}
+
+#[test]
+fn test_retain_incremental() {
+    let mut map = IM::new();
+    for i in 0..10 {
+        map.insert(i * 10);
+    }
+    map.remove(1);
+
+    let mut seen = Vec::new();
+    let next = map.retain_incremental(0, 4, |k, _| {
+        seen.push(k);
+        k != 2
+    });
+    assert_eq!(next, 4);
+    assert_eq!(seen, [0, 2, 3]);
+    assert_eq!(map.len(), 8);
+
+    let next = map.retain_incremental(next, 100, |k, _| k < 8);
+    assert_eq!(next, 10);
+    assert_eq!(map.keys().collect::<Vec<_>>(), [0, 3, 4, 5, 6, 7]);
+
+    // Nothing left to do.
+    assert_eq!(map.retain_incremental(next, 4, |_, _| false), 10);
+    assert_eq!(
+        map.retain_incremental(usize::MAX, usize::MAX, |_, _| false),
+        10
+    );
+    assert_eq!(map.len(), 6);
+
+    // The removed slots are reused.
+    let mut keys: Vec<_> = (0..4).map(|_| map.insert(0)).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, [1, 2, 8, 9]);
+}
+
+#[test]
+fn test_eq_slice() {
+    let mut map = IM::new();
+    assert_eq!(map, &[][..]);
+
+    for i in 0..5 {
+        map.insert(i * 10);
+    }
+    map.remove(1);
+    map.remove(4);
+
+    assert_eq!(map, &[(0, 0), (2, 20), (3, 30)][..]);
+    assert!(map == [(0, 0), (2, 20), (3, 30)][..]);
+
+    // Keys, values, order and length all have to match.
+    assert_ne!(map, &[(0, 0), (1, 20), (3, 30)][..]);
+    assert_ne!(map, &[(0, 0), (2, 21), (3, 30)][..]);
+    assert_ne!(map, &[(0, 0), (3, 30), (2, 20)][..]);
+    assert_ne!(map, &[(0, 0), (2, 20)][..]);
+    assert_ne!(map, &[(0, 0), (2, 20), (3, 30), (4, 40)][..]);
+
+    // Comparing two maps still works.
+    assert_eq!(map, map.clone());
+}
+
+#[test]
+fn test_update_many() {
+    let mut map = IM::new();
+    for i in 0..5 {
+        map.insert(i);
+    }
+    map.remove(2);
+
+    let mut visited = Vec::new();
+    map.update_many(&[4, 2, 0, 4, 100], |k, v| {
+        visited.push(k);
+        *v += 10;
+    });
+    assert_eq!(visited, [4, 0, 4]);
+    assert_eq!(map, &[(0, 10), (1, 1), (3, 3), (4, 24)][..]);
+
+    map.update_many(&[], |_, _| panic!("no keys to update"));
+    assert_eq!(map.len(), 4);
+}
+
+#[test]
+fn test_handle_iters() {
+    let mut map = IM::new();
+    let a = map.insert_handle(1);
+    let b = map.insert_handle(2);
+    let c = map.insert_handle(3);
+    map.remove(b.key());
+
+    assert_eq!(map.handles().collect::<Vec<_>>(), [a, c]);
+    assert_eq!(
+        map.handle_iter().map(|(h, v)| (h, *v)).collect::<Vec<_>>(),
+        [(a, 1), (c, 3)]
+    );
+    for (handle, val) in map.handle_iter() {
+        assert_eq!(handle.resolve(&map), Some(val));
+    }
+    assert_eq!(map.into_handle_iter().collect::<Vec<_>>(), [(a, 1), (c, 3)]);
+}
+
+#[test]
+fn test_stats() {
+    let mut map = IM::new();
+    let stats = map.stats();
+    assert_eq!(stats.len, 0);
+    assert_eq!(stats.free_slots, 0);
+    assert_eq!(stats.max_occupied, None);
+    assert_eq!(stats.fragmentation, 0.0);
+
+    for i in 0..10 {
+        map.insert(i);
+    }
+    assert_eq!(map.stats().max_occupied, Some(9));
+    assert_eq!(map.stats().fragmentation, 0.0);
+
+    map.retain(|k, _| k % 4 == 0);
+    let stats = map.stats();
+    assert_eq!(stats.len, map.len());
+    assert_eq!(stats.capacity, map.capacity());
+    assert_eq!(stats.free_slots, 7);
+    assert_eq!(stats.max_occupied, Some(8));
+    assert_eq!(stats.fragmentation, 0.7);
+
+    // Trailing free slots count until they are trimmed.
+    map.shrink_to_fit();
+    let stats = map.stats();
+    assert_eq!(stats.free_slots, 6);
+    assert_eq!(stats.max_occupied, Some(8));
+
+    // Removing every entry leaves only free slots behind.
+    map.retain(|_, _| false);
+    let stats = map.stats();
+    assert_eq!(stats.len, 0);
+    assert_eq!(stats.free_slots, 9);
+    assert_eq!(stats.max_occupied, None);
+    assert_eq!(stats.fragmentation, 1.0);
+}
+
+#[cfg(feature = "debug_checks")]
+#[test]
+fn test_remove_debug() {
+    use index_map::FreeListOp;
+
+    let mut map = IM::new();
+    for i in 0..5 {
+        map.insert(i);
+    }
+
+    let (val, op) = map.remove_debug(3).unwrap();
+    assert_eq!(val, 3);
+    assert_eq!(
+        op,
+        FreeListOp {
+            new_head: 3,
+            next: None,
+            prev_head: None
+        }
+    );
+
+    let (_, op) = map.remove_debug(0).unwrap();
+    assert_eq!(
+        op,
+        FreeListOp {
+            new_head: 0,
+            next: Some(3),
+            prev_head: Some(3)
+        }
+    );
+
+    // Reinserting pops the head, so the next removal links to what was behind it.
+    assert_eq!(map.insert(10), 0);
+    let (_, op) = map.remove_debug(4).unwrap();
+    assert_eq!(
+        op,
+        FreeListOp {
+            new_head: 4,
+            next: Some(3),
+            prev_head: Some(3)
+        }
+    );
+
+    assert!(map.remove_debug(4).is_none());
+    assert!(map.remove_debug(100).is_none());
+    assert_eq!(map.len(), 3);
+}
+
+#[test]
+fn test_drain_map() {
+    let mut map = IM::new();
+    for i in 0..6 {
+        map.insert(i);
+    }
+    map.remove(1);
+    map.remove(4);
+
+    // The closure may borrow from the caller.
+    let offset = 100;
+    let mut iter = map.drain_map(|k, v| k * offset + v);
+    assert_eq!(iter.next(), Some(0));
+    assert_eq!(iter.next(), Some(202));
+    drop(iter);
+
+    // Dropping the iterator early still empties and resets the map.
+    assert!(map.is_empty());
+    assert_eq!(map.iter().count(), 0);
+    assert_eq!(map.insert(7), 0);
+    assert_eq!(map.insert(8), 1);
+}
+
+#[test]
+fn test_capacity_floor() {
+    let mut map = IM::with_capacity_floor(32);
+    assert!(map.capacity() >= 32);
+
+    for i in 0..100 {
+        map.insert(i);
+    }
+    map.retain(|k, _| k < 4);
+
+    map.shrink_to_fit();
+    assert!(map.capacity() >= 32);
+    assert!(map.capacity() < 100);
+
+    map.shrink_with_slack(2);
+    assert!(map.capacity() >= 32);
+
+    map.maybe_shrink(0.5);
+    assert!(map.capacity() >= 32);
+
+    // Clones and partitions keep the floor.
+    let mut clone = map.clone();
+    clone.shrink_to_fit();
+    assert!(clone.capacity() >= 32);
+
+    let (mut low, mut high) = map.partition(|k, _| k < 2);
+    low.shrink_to_fit();
+    high.shrink_to_fit();
+    assert!(low.capacity() >= 32);
+    assert!(high.capacity() >= 32);
+
+    // Without a floor, the map shrinks as far as its keys allow.
+    let mut map = IM::with_capacity(32);
+    map.insert(0);
+    map.shrink_to_fit();
+    assert!(map.capacity() < 32);
+}
+
+#[test]
+fn test_replace_entry_with() {
+    use index_map::Entry;
+
+    let mut map = IM::with_insertion_order_tracking();
+    let a = map.insert(1);
+    let b = map.insert(5);
+    let c = map.insert(3);
+
+    let entry = match map.entry(b) {
+        Entry::Occupied(o) => o.replace_entry_with(|k, v| Some(k * 100 + v)),
+        Entry::Vacant(_) => unreachable!(),
+    };
+    match entry {
+        Entry::Occupied(o) => assert_eq!(*o.get(), 105),
+        Entry::Vacant(_) => panic!("value should have been put back"),
+    }
+
+    // The replaced value keeps its place in the insertion order.
+    let order: Vec<_> = map.iter_insertion_order().map(|(k, _)| k).collect();
+    assert_eq!(order, [a, b, c]);
+
+    let entry = match map.entry(a) {
+        Entry::Occupied(o) => o.replace_entry_with(|_, _| None),
+        Entry::Vacant(_) => unreachable!(),
+    };
+    assert!(matches!(entry, Entry::Vacant(ref v) if v.key() == a));
+    assert_eq!(map.len(), 2);
+    assert!(!map.contains_key(a));
+    assert_eq!(map.insert(7), a);
+}
+
+#[test]
+fn test_try_from_iter() {
+    let map = IM::try_from_iter((0..100).map(|i| i * 2)).unwrap();
+    assert_eq!(map.len(), 100);
+    assert!(map.is_dense());
+    assert_eq!(map[99], 198);
+
+    // Iterators without an exact size hint grow the map as they go.
+    let map = IM::try_from_iter((0..100).filter(|i| i % 3 == 0)).unwrap();
+    assert_eq!(map.len(), 34);
+    assert_eq!(map[33], 99);
+
+    let map = IM::<i32>::try_from_iter(None).unwrap();
+    assert!(map.is_empty());
+    assert_eq!(map.capacity(), 0);
+}
+
+#[test]
+fn test_try_from_iter_overflow() {
+    // Claims far more items than could ever be allocated.
+    struct Huge;
+    impl Iterator for Huge {
+        type Item = u64;
+        fn next(&mut self) -> Option<u64> {
+            None
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (usize::MAX, None)
+        }
+    }
+
+    assert!(IM::try_from_iter(Huge).is_err());
+}
+
+#[test]
+fn test_vacant_entry() {
+    let mut map = IM::with_insertion_order_tracking();
+
+    // Fresh map: keys are handed out at the end.
+    for i in 0..4 {
+        let entry = map.vacant_entry();
+        assert_eq!(entry.key(), i);
+        assert_eq!(*entry.insert(i * 10), i * 10);
+    }
+
+    map.remove(1);
+    map.remove(2);
+
+    // Freed keys are reused in the same order as `insert` would.
+    let mut other = map.clone();
+    for _ in 0..3 {
+        let entry = map.vacant_entry();
+        let key = entry.key();
+        entry.insert(key);
+        assert_eq!(other.insert(key), key);
+    }
+    assert_eq!(map.keys().collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+
+    // Dropping the entry without inserting changes nothing.
+    let before = map.clone();
+    let key = map.vacant_entry().key();
+    assert_eq!(map, before);
+    assert_eq!(map.insert(50), key);
+
+    // Values inserted through the entry are tracked like any other.
+    let newest = map.iter_insertion_order().next_back().map(|(k, _)| k);
+    assert_eq!(newest, Some(key));
+}
+
+#[test]
+fn test_insert_after_clear() {
+    let mut map = IM::new();
+    for i in 0..5 {
+        map.insert(i);
+    }
+    map.remove(3);
+    map.remove(1);
+
+    map.clear();
+    assert_eq!(map.insert(10), 0);
+    assert_eq!(map.len(), 1);
+    assert_eq!(map, &[(0, 10)][..]);
+    assert_eq!(map.insert(11), 1);
+}
+
+#[test]
+fn test_insert_after_drain() {
+    let mut map = IM::new();
+    for i in 0..5 {
+        map.insert(i);
+    }
+    map.remove(3);
+    map.remove(1);
+
+    assert_eq!(map.drain().collect::<Vec<_>>(), [(0, 0), (2, 2), (4, 4)]);
+    assert_eq!(map.insert(10), 0);
+    assert_eq!(map.insert(11), 1);
+    assert_eq!(map, &[(0, 10), (1, 11)][..]);
+
+    // Dropping the iterator early still removes every slot.
+    map.remove(0);
+    let mut drain = map.drain();
+    assert_eq!(drain.next(), Some((1, 11)));
+    drop(drain);
+    assert!(map.is_empty());
+    assert_eq!(map.insert(20), 0);
+
+    map.remove(0);
+    map.drain();
+    assert_eq!(map.insert(30), 0);
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn test_eq_ignores_layout() {
+    let mut a = IM::new();
+    a.insert('a');
+    a.insert('b');
+    a.insert('c');
+    a.remove(1);
+    a.insert('b');
+
+    let mut b = IM::new();
+    b.insert('a');
+    b.insert('b');
+    b.insert('c');
+    assert_eq!(a, b);
+
+    // Different free slots and free list order, same entries.
+    let mut a = IM::with_capacity(10);
+    for c in "abcde".chars() {
+        a.insert(c);
+    }
+    a.remove(1);
+    a.remove(3);
+    a.remove(4);
+
+    let mut b = IM::new();
+    for c in "axcyz".chars() {
+        b.insert(c);
+    }
+    b.remove(4);
+    b.remove(3);
+    b.remove(1);
+    b.shrink_to_fit();
+    assert_eq!(a, b);
+    assert_eq!(a.cmp(&b), std::cmp::Ordering::Equal);
+
+    // Same values at different keys are not equal.
+    let mut c = IM::new();
+    c.insert('a');
+    c.insert('c');
+    assert_ne!(a, c);
+    assert_ne!(a, IM::new());
+    assert_eq!(IM::<char>::new(), IM::with_capacity(4));
+}
+
+#[test]
+fn test_ord() {
+    let from = |pairs: &[(usize, i32)]| {
+        let mut map = IM::new();
+        for &(key, val) in pairs {
+            map.entry(key).or_insert(val);
+        }
+        map
+    };
+
+    assert!(from(&[(0, 1)]) < from(&[(0, 2)]));
+    assert!(from(&[(0, 1)]) < from(&[(0, 1), (1, 0)]));
+    // Keys are compared before values.
+    assert!(from(&[(0, 9)]) < from(&[(1, 0)]));
+    assert!(IM::new() < from(&[(5, 0)]));
+
+    let mut a = from(&[(0, 1), (2, 3)]);
+    a.insert(7);
+    a.remove(1);
+    assert_eq!(a.cmp(&from(&[(2, 3), (0, 1)])), std::cmp::Ordering::Equal);
+}
+
+#[test]
+fn test_hash() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use std::hash::{Hash, Hasher};
+
+    fn hash(map: &IM<&str>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        map.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let mut a = IM::with_capacity(8);
+    for s in ["a", "b", "c", "d"] {
+        a.insert(s);
+    }
+    a.remove(1);
+    a.remove(3);
+
+    let mut b = IM::new();
+    b.insert("a");
+    b.insert("x");
+    b.insert("c");
+    b.remove(1);
+
+    assert_eq!(a, b);
+    assert_eq!(hash(&a), hash(&b));
+
+    let mut set = HashSet::new();
+    set.insert(a);
+    assert!(set.contains(&b));
+
+    b.insert("b");
+    assert!(!set.contains(&b));
+    assert_ne!(hash(&IM::new()), hash(&b));
+}
+
+#[test]
+fn test_from_iter() {
+    let map: IM<_> = vec!["a", "b", "c"].into_iter().collect();
+    assert_eq!(map.len(), 3);
+    assert_eq!(map, &[(0, "a"), (1, "b"), (2, "c")][..]);
+    assert!(map.capacity() >= 3);
+
+    // Iterators with an inexact size hint work too.
+    let map: IM<_> = (0..10).filter(|i| i % 2 == 1).collect();
+    assert_eq!(map.keys().collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+    assert_eq!(map[4], 9);
+
+    let map: IM<i32> = std::iter::empty().collect();
+    assert!(map.is_empty());
+    assert_eq!(map.capacity(), 0);
+}
+
+#[test]
+fn test_extend() {
+    let mut map = IM::new();
+    for i in 0..5 {
+        map.insert(i);
+    }
+    map.remove(1);
+    map.remove(3);
+
+    // The most recently freed slot is reused first, then new keys are appended.
+    map.extend(vec![10, 11, 12, 13]);
+    assert_eq!(
+        map,
+        &[(0, 0), (1, 11), (2, 2), (3, 10), (4, 4), (5, 12), (6, 13)][..]
+    );
+    assert!(map.is_dense());
+
+    map.extend(std::iter::empty());
+    assert_eq!(map.len(), 7);
+
+    // Iterators with an inexact size hint work too.
+    map.remove(0);
+    map.extend((0..6).filter(|i| i % 2 == 0));
+    assert_eq!(map.keys().collect::<Vec<_>>(), (0..9).collect::<Vec<_>>());
+    assert_eq!(map[0], 0);
+    assert_eq!(map[8], 4);
+}
+
+#[test]
+fn test_into_keys_values() {
+    let mut map = IM::new();
+    for i in 0..6 {
+        map.insert(i.to_string());
+    }
+    map.remove(0);
+    map.remove(3);
+    map.remove(5);
+
+    let keys = map.clone().into_keys();
+    assert_eq!(keys.len(), 3);
+    assert_eq!(keys.collect::<Vec<_>>(), [1, 2, 4]);
+    assert_eq!(map.clone().into_keys().rev().collect::<Vec<_>>(), [4, 2, 1]);
+
+    let mut values = map.into_values();
+    assert_eq!(values.len(), 3);
+    assert_eq!(values.next().as_deref(), Some("1"));
+    assert_eq!(values.len(), 2);
+    assert_eq!(values.collect::<Vec<_>>(), ["2", "4"]);
+
+    assert_eq!(IM::<i32>::new().into_values().count(), 0);
+}
+
+#[test]
+fn test_iter_double_ended() {
+    let mut map = IM::new();
+    for i in 0..8 {
+        map.insert(i * 10);
+    }
+    for key in [0, 3, 4, 7] {
+        map.remove(key);
+    }
+
+    assert_eq!(
+        map.iter().rev().map(|(k, v)| (k, *v)).collect::<Vec<_>>(),
+        [(6, 60), (5, 50), (2, 20), (1, 10)]
+    );
+    assert_eq!(map.keys().next_back(), Some(6));
+    assert_eq!(
+        map.values().rev().copied().collect::<Vec<_>>(),
+        [60, 50, 20, 10]
+    );
+
+    // Alternating ends meets in the middle without yielding anything twice.
+    let mut iter = map.iter();
+    assert_eq!(iter.next(), Some((1, &10)));
+    assert_eq!(iter.next_back(), Some((6, &60)));
+    assert_eq!(iter.len(), 2);
+    assert_eq!(iter.next_back(), Some((5, &50)));
+    assert_eq!(iter.next(), Some((2, &20)));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next_back(), None);
+
+    let mut keys = map.keys();
+    assert_eq!(keys.next_back(), Some(6));
+    assert_eq!(keys.next(), Some(1));
+    assert_eq!(keys.collect::<Vec<_>>(), [2, 5]);
+
+    let mut values = map.values();
+    assert_eq!(values.next(), Some(&10));
+    assert_eq!(values.rev().collect::<Vec<_>>(), [&60, &50, &20]);
+}
+
+#[test]
+fn test_iter_mut_double_ended() {
+    let mut map = IM::new();
+    for i in 1..=9 {
+        map.insert(i);
+    }
+    for key in [0, 4, 8] {
+        map.remove(key);
+    }
+
+    // Double every value, alternating between both ends.
+    let mut iter = map.iter_mut();
+    let mut visited = Vec::new();
+    let mut front = true;
+    loop {
+        let next = if front { iter.next() } else { iter.next_back() };
+        match next {
+            Some((k, v)) => {
+                *v *= 2;
+                visited.push(k);
+            }
+            None => break,
+        }
+        front = !front;
+    }
+    assert_eq!(visited, [1, 7, 2, 6, 3, 5]);
+    assert_eq!(iter.next_back(), None);
+    assert_eq!(
+        map.values().copied().collect::<Vec<_>>(),
+        [4, 6, 8, 12, 14, 16]
+    );
+
+    for v in map.values_mut().rev().take(2) {
+        *v = 0;
+    }
+    assert_eq!(
+        map.values().copied().collect::<Vec<_>>(),
+        [4, 6, 8, 12, 0, 0]
+    );
+
+    // Keys stay correct on the upper half of a split.
+    let (_, mut high) = map.split_at_key_mut(4);
+    assert_eq!(high.next_back().map(|(k, _)| k), Some(7));
+    assert_eq!(high.next().map(|(k, _)| k), Some(5));
+    assert_eq!(high.next_back().map(|(k, _)| k), Some(6));
+    assert!(high.next().is_none());
+}
+
+#[test]
+fn test_try_insert() {
+    use index_map::OccupiedError;
+
+    let mut map = IM::new();
+    assert_eq!(map.try_insert(3, String::from("a")), Ok(()));
+
+    let err = map.try_insert(3, String::from("b")).unwrap_err();
+    assert_eq!(err.key, 3);
+    assert_eq!(err.value, "b");
+    assert_eq!(err.to_string(), "key 3 is already occupied");
+    assert_eq!(map[3], "a");
+    assert_eq!(map.len(), 1);
+
+    // The padding before the key is free, so it can be filled.
+    assert_eq!(map.try_insert(1, String::from("c")), Ok(()));
+    assert_eq!(
+        map.try_insert(1, String::new()),
+        Err(OccupiedError {
+            key: 1,
+            value: String::new()
+        })
+    );
+    assert_eq!(map.keys().collect::<Vec<_>>(), [1, 3]);
+}
+
+#[test]
+fn test_contains_value() {
+    let mut map = IM::new();
+    assert!(!map.contains_value(&0));
+
+    for i in 0..6 {
+        map.insert(i * 10);
+    }
+    map.remove(1);
+    map.remove(4);
+
+    assert!(map.contains_value(&0));
+    assert!(map.contains_value(&50));
+    assert!(!map.contains_value(&10));
+    assert!(!map.contains_value(&40));
+    assert!(!map.contains_value(&7));
+
+    // The freed slots don't hold stale values.
+    map.insert(70);
+    assert!(map.contains_value(&70));
+    assert!(!map.contains_value(&40));
+}
+
+#[test]
+fn test_find_key() {
+    struct Process {
+        name: &'static str,
+        running: bool,
+    }
+
+    let mut map = IM::new();
+    for (name, running) in [
+        ("init", true),
+        ("vim", false),
+        ("cargo", true),
+        ("rls", true),
+    ] {
+        map.insert(Process { name, running });
+    }
+    map.remove(2);
+
+    assert_eq!(map.find_key(|p| p.name == "rls"), Some(3));
+    assert_eq!(map.find_key(|p| p.name == "cargo"), None);
+    assert_eq!(map.find_key(|p| !p.running), Some(1));
+
+    // The first match in ascending key order wins.
+    assert_eq!(map.find_key(|p| p.running), Some(0));
+    map.remove(0);
+    assert_eq!(map.find_key(|p| p.running), Some(3));
+}
+
+#[test]
+fn test_get_disjoint_mut() {
+    let mut map = IM::new();
+    for i in 0..5 {
+        map.insert(vec![i]);
+    }
+    map.remove(2);
+
+    // Move data between two entries without cloning.
+    if let Some([a, b]) = map.get_disjoint_mut([4, 0]) {
+        a.append(b);
+    }
+    assert_eq!(map[4], [4, 0]);
+    assert!(map[0].is_empty());
+
+    let [a, b, c] = map.get_disjoint_mut([1, 3, 4]).unwrap();
+    a.push(10);
+    b.push(30);
+    c.clear();
+    assert_eq!(map[1], [1, 10]);
+    assert_eq!(map[3], [3, 30]);
+    assert!(map[4].is_empty());
+
+    // Aliasing or missing keys are rejected without touching the map.
+    assert!(map.get_disjoint_mut([1, 3, 1]).is_none());
+    assert!(map.get_disjoint_mut([1, 2]).is_none());
+    assert!(map.get_disjoint_mut([5]).is_none());
+    assert_eq!(map.len(), 4);
+
+    assert_eq!(map.get_disjoint_mut::<usize, 0>([]), Some([]));
+
+    let h = map.insert_handle(vec![]);
+    assert!(map.get_disjoint_mut([h]).is_some());
+}
+
+#[test]
+fn test_swap() {
+    let mut map = IM::new();
+    for c in "abcd".chars() {
+        map.insert(c);
+    }
+    map.remove(2);
+
+    assert!(map.swap(0, 3));
+    assert_eq!(map, &[(0, 'd'), (1, 'b'), (3, 'a')][..]);
+
+    // One key absent: nothing changes.
+    assert!(!map.swap(1, 2));
+    assert!(!map.swap(2, 1));
+    assert!(!map.swap(1, 100));
+    assert_eq!(map, &[(0, 'd'), (1, 'b'), (3, 'a')][..]);
+
+    // Swapping a key with itself.
+    assert!(map.swap(1, 1));
+    assert!(!map.swap(2, 2));
+    assert_eq!(map, &[(0, 'd'), (1, 'b'), (3, 'a')][..]);
+
+    // The free list is untouched.
+    assert_eq!(map.insert('e'), 2);
+}
+
+#[test]
+fn test_first_last_key_value() {
+    let mut map = IM::new();
+    assert_eq!(map.first_key_value(), None);
+    assert_eq!(map.last_key_value(), None);
+
+    for i in 0..8 {
+        map.insert(i * 10);
+    }
+    assert_eq!(map.first_key_value(), Some((0, &0)));
+    assert_eq!(map.last_key_value(), Some((7, &70)));
+
+    // Leading and trailing free slots are skipped.
+    for key in [0, 1, 6, 7, 3] {
+        map.remove(key);
+    }
+    assert_eq!(map.first_key_value(), Some((2, &20)));
+    assert_eq!(map.last_key_value(), Some((5, &50)));
+
+    map.remove(2);
+    map.remove(4);
+    assert_eq!(map.first_key_value(), Some((5, &50)));
+    assert_eq!(map.last_key_value(), Some((5, &50)));
+
+    map.remove(5);
+    assert_eq!(map.first_key_value(), None);
+    assert_eq!(map.last_key_value(), None);
+}
+
+#[test]
+fn test_retain_count() {
+    let mut map = IM::new();
+    for i in 0..20 {
+        map.insert(i);
+    }
+    map.remove(3);
+
+    let mut rejected = 0;
+    let removed = map.retain(|_, v| {
+        let keep = *v % 3 != 0;
+        if !keep {
+            rejected += 1;
+        }
+        keep
+    });
+    assert_eq!(removed, rejected);
+    assert_eq!(removed, 6);
+    assert_eq!(map.len(), 19 - 6);
+
+    assert_eq!(map.retain(|_, _| true), 0);
+    assert_eq!(map.len(), 13);
+
+    assert_eq!(map.retain_ref(|k, _| k < 10), 7);
+    assert_eq!(map.retain_with(&mut (), |_, _, v| *v != 1), 1);
+    assert_eq!(map.keys().collect::<Vec<_>>(), [2, 4, 5, 7, 8]);
+
+    // The freed slots are reused as usual.
+    assert_eq!(map.retain(|_, _| false), 5);
+    assert!(map.is_empty());
+    assert_eq!(map.insert(0), 8);
+}
@@ -0,0 +1,69 @@
+// Randomized check that keys of entries which remain in the map are never changed.
+
+use index_map::IndexMap;
+use std::collections::HashMap;
+
+/// Small xorshift generator, so the test is deterministic and needs no extra dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn check(map: &IndexMap<u64>, shadow: &HashMap<usize, u64>) {
+    assert_eq!(map.len(), shadow.len());
+    for (&key, value) in shadow {
+        assert_eq!(map.get(key), Some(value));
+    }
+    for (key, value) in map {
+        assert_eq!(shadow.get(&key), Some(value));
+    }
+}
+
+#[test]
+fn test_stable_keys() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut map = IndexMap::new();
+    let mut shadow = HashMap::new();
+    let mut next_value = 0;
+
+    for _ in 0..5000 {
+        match rng.below(100) {
+            0..=44 => {
+                let key = map.insert(next_value);
+                assert_eq!(shadow.insert(key, next_value), None);
+                next_value += 1;
+            }
+            45..=79 => {
+                let key = rng.below(map.len() * 2 + 1);
+                assert_eq!(map.remove(key), shadow.remove(&key));
+            }
+            80..=89 => {
+                let key = rng.below(map.len() * 2 + 1);
+                if let Some(value) = map.get_mut(key) {
+                    *value += 1_000_000;
+                    *shadow.get_mut(&key).unwrap() += 1_000_000;
+                } else {
+                    assert!(!shadow.contains_key(&key));
+                }
+            }
+            90..=94 => {
+                let modulus = rng.below(5) as u64 + 2;
+                map.retain(|_, value| *value % modulus != 0);
+                shadow.retain(|_, value| *value % modulus != 0);
+            }
+            _ => map.shrink_to_fit(),
+        }
+
+        check(&map, &shadow);
+    }
+}